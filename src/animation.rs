@@ -0,0 +1,296 @@
+use std::path::Path;
+
+use bevy::{
+    gltf::Gltf,
+    prelude::*,
+    render::{
+        mesh::{
+            skinning::{SkinnedMesh, SkinnedMeshInverseBindposes},
+            VertexAttributeValues,
+        },
+        primitives::Aabb,
+    },
+    window::PrimaryWindow,
+};
+
+use crate::{
+    resource::{
+        AssetPath, LiveCaptureOperationSettings, ModelAnimation, OperationWindowRelatedEntities,
+    },
+    types::{AnimationFrameRecord, DEFAULT_ANIMATION_FRAME_RATE},
+};
+
+// find the animation clips of the model once it is loaded and give the animation graph to its animation players
+pub fn setup_model_animation(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    asset_path: Res<AssetPath>,
+    operation_window: Res<OperationWindowRelatedEntities>,
+    gltfs: Res<Assets<Gltf>>,
+    clips: Res<Assets<AnimationClip>>,
+    mut graphs: ResMut<Assets<AnimationGraph>>,
+    mut model_animation: ResMut<ModelAnimation>,
+    players: Query<Entity, (With<AnimationPlayer>, Without<AnimationGraphHandle>)>,
+    parent_query: Query<&Parent>,
+) {
+    let scene_entity = match operation_window.current_scene_entity {
+        Some(scene_entity) => scene_entity,
+        None => return,
+    };
+
+    // a new model was spawned, the entry of a glTF scene has the scene label after the file path
+    if model_animation.scene_entity != Some(scene_entity) {
+        let model_path = &asset_path.models_path[asset_path.current_model_path_count as usize];
+        let file_path = model_path.split('#').next().unwrap_or(model_path);
+        let is_gltf = match Path::new(file_path).extension() {
+            Some(ext) => ext == "glb" || ext == "gltf",
+            None => false,
+        };
+        *model_animation = ModelAnimation {
+            scene_entity: Some(scene_entity),
+            gltf_handler: if is_gltf {
+                Some(asset_server.load(file_path.to_string()))
+            } else {
+                None
+            },
+            loaded: !is_gltf,
+            ..default()
+        };
+    }
+
+    if !model_animation.loaded {
+        let gltf = match model_animation
+            .gltf_handler
+            .as_ref()
+            .and_then(|gltf_handler| gltfs.get(gltf_handler))
+        {
+            Some(gltf) => gltf,
+            None => return,
+        };
+        let clip_names = gltf
+            .animations
+            .iter()
+            .map(|clip| {
+                match gltf
+                    .named_animations
+                    .iter()
+                    .find(|(_, named_clip)| *named_clip == clip)
+                {
+                    Some((name, _)) => name.to_string(),
+                    None => "".to_string(),
+                }
+            })
+            .collect();
+        let clip_durations = gltf
+            .animations
+            .iter()
+            .map(|clip| match clips.get(clip) {
+                Some(clip) => clip.duration(),
+                None => 0.0,
+            })
+            .collect();
+        if !gltf.animations.is_empty() {
+            println!("🎞️ {} animation clip(s) found", gltf.animations.len());
+            let (graph, nodes) = AnimationGraph::from_clips(gltf.animations.iter().cloned());
+            model_animation.graph = Some(graphs.add(graph));
+            model_animation.nodes = nodes;
+        }
+        model_animation.clip_names = clip_names;
+        model_animation.clip_durations = clip_durations;
+        model_animation.loaded = true;
+    }
+
+    // the animation players are only there once the scene is spawned
+    if let Some(graph) = model_animation.graph.as_ref() {
+        for player in players.iter() {
+            if parent_query
+                .iter_ancestors(player)
+                .any(|ancestor| ancestor == scene_entity)
+            {
+                commands
+                    .entity(player)
+                    .insert(AnimationGraphHandle(graph.clone()));
+            }
+        }
+    }
+}
+
+// pose the model at the time of the current clip, the clip is kept paused so that every captured frame get the exact
+// time it was asked for
+pub fn pose_model_animation(
+    model_animation: Res<ModelAnimation>,
+    mut players: Query<(&mut AnimationPlayer, &AnimationGraphHandle)>,
+) {
+    let node = match model_animation.nodes.get(model_animation.current_clip) {
+        Some(node) => *node,
+        None => return,
+    };
+    for (mut player, graph) in players.iter_mut() {
+        if Some(&graph.0) != model_animation.graph.as_ref() {
+            continue;
+        }
+        if !player.is_playing_animation(node) {
+            player.stop_all();
+            player.play(node).pause();
+        }
+        if let Some(active_animation) = player.animation_mut(node) {
+            active_animation.seek_to(model_animation.time);
+        }
+    }
+}
+
+// P play / pause the current clip, [ and ] step one frame backward / forward, N switch to the next clip, the clip and
+// time are shown in the window title as anything drawn in the window would end up in the snapshots
+pub fn animation_scrubber(
+    keys: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+    live_capture_settings: Res<LiveCaptureOperationSettings>,
+    mut model_animation: ResMut<ModelAnimation>,
+    mut window_query: Query<&mut Window, Without<PrimaryWindow>>,
+) {
+    if model_animation.nodes.is_empty() {
+        return;
+    }
+
+    let frame_rate = match &live_capture_settings.animation_capture {
+        Some(animation_capture) if animation_capture.frame_rate > 0.0 => {
+            animation_capture.frame_rate
+        }
+        _ => DEFAULT_ANIMATION_FRAME_RATE,
+    };
+    if keys.just_pressed(KeyCode::KeyP) {
+        model_animation.playing = !model_animation.playing;
+    }
+    if keys.just_pressed(KeyCode::KeyN) {
+        model_animation.current_clip =
+            (model_animation.current_clip + 1) % model_animation.nodes.len();
+        model_animation.time = 0.0;
+    }
+    let duration = model_animation.clip_durations[model_animation.current_clip];
+    if keys.just_pressed(KeyCode::BracketRight) {
+        model_animation.playing = false;
+        model_animation.time = (model_animation.time + 1.0 / frame_rate).min(duration);
+    }
+    if keys.just_pressed(KeyCode::BracketLeft) {
+        model_animation.playing = false;
+        model_animation.time = (model_animation.time - 1.0 / frame_rate).max(0.0);
+    }
+    // the clip loop while playing
+    if model_animation.playing {
+        model_animation.time += time.delta_secs();
+        if model_animation.time > duration {
+            model_animation.time = 0.0;
+        }
+    }
+
+    let title = format!(
+        "Interactive 📱 [🎞️ clip {}/{} {} {:.2}s / {:.2}s {}]",
+        model_animation.current_clip + 1,
+        model_animation.nodes.len(),
+        model_animation.clip_names[model_animation.current_clip],
+        model_animation.time,
+        duration,
+        if model_animation.playing {
+            "▶"
+        } else {
+            "⏸"
+        }
+    );
+    for mut window in window_query.iter_mut() {
+        if window.title != title {
+            window.title = title.clone();
+        }
+    }
+}
+
+// the clip and time the model is posed at, None when the model has no animation
+pub fn animation_frame_record(model_animation: &ModelAnimation) -> Option<AnimationFrameRecord> {
+    if model_animation.nodes.is_empty() {
+        return None;
+    }
+
+    return Some(AnimationFrameRecord {
+        clip_index: model_animation.current_clip,
+        clip_name: model_animation.clip_names[model_animation.current_clip].clone(),
+        time: model_animation.time,
+    });
+}
+
+// bevy keep the bounding box of a skinned mesh in its rest pose, the box is computed again from the posed vertices so
+// that the annotations of an animation frame match the image ( the morph targets are left out )
+pub fn update_skinned_mesh_bounds(
+    model_animation: Res<ModelAnimation>,
+    meshes: Res<Assets<Mesh>>,
+    inverse_bindposes: Res<Assets<SkinnedMeshInverseBindposes>>,
+    mut skinned_meshes: Query<(&Mesh3d, &SkinnedMesh, &GlobalTransform, &mut Aabb)>,
+    joints: Query<&GlobalTransform>,
+) {
+    if model_animation.nodes.is_empty() {
+        return;
+    }
+
+    for (mesh, skinned_mesh, global_trans, mut aabb) in skinned_meshes.iter_mut() {
+        let mesh = match meshes.get(&mesh.0) {
+            Some(mesh) => mesh,
+            None => continue,
+        };
+        let inverse_bindposes = match inverse_bindposes.get(&skinned_mesh.inverse_bindposes) {
+            Some(inverse_bindposes) => inverse_bindposes,
+            None => continue,
+        };
+        let (positions, joint_indices, joint_weights) = match (
+            mesh.attribute(Mesh::ATTRIBUTE_POSITION),
+            mesh.attribute(Mesh::ATTRIBUTE_JOINT_INDEX),
+            mesh.attribute(Mesh::ATTRIBUTE_JOINT_WEIGHT),
+        ) {
+            (
+                Some(VertexAttributeValues::Float32x3(positions)),
+                Some(joint_indices),
+                Some(VertexAttributeValues::Float32x4(joint_weights)),
+            ) => (positions, joint_indices, joint_weights),
+            _ => continue,
+        };
+        let joint_indices: Vec<[u16; 4]> = match joint_indices {
+            VertexAttributeValues::Uint16x4(joint_indices) => joint_indices.clone(),
+            VertexAttributeValues::Uint8x4(joint_indices) => joint_indices
+                .iter()
+                .map(|indices| indices.map(|index| index as u16))
+                .collect(),
+            _ => continue,
+        };
+
+        // the joint matrices move a vertex from the mesh's bind pose to world space
+        let joint_matrices: Vec<Mat4> = skinned_mesh
+            .joints
+            .iter()
+            .zip(inverse_bindposes.iter())
+            .map(|(joint, inverse_bindpose)| match joints.get(*joint) {
+                Ok(joint_trans) => joint_trans.compute_matrix() * *inverse_bindpose,
+                Err(_) => Mat4::IDENTITY,
+            })
+            .collect();
+        let world_to_mesh = global_trans.compute_matrix().inverse();
+
+        let mut min = Vec3::MAX;
+        let mut max = Vec3::MIN;
+        for ((position, indices), weights) in positions
+            .iter()
+            .zip(joint_indices.iter())
+            .zip(joint_weights.iter())
+        {
+            let mut skin_matrix = Mat4::ZERO;
+            for (index, weight) in indices.iter().zip(weights.iter()) {
+                if let Some(joint_matrix) = joint_matrices.get(*index as usize) {
+                    skin_matrix += *joint_matrix * *weight;
+                }
+            }
+            let posed_position =
+                world_to_mesh.transform_point3(skin_matrix.transform_point3(Vec3::from(*position)));
+            min = min.min(posed_position);
+            max = max.max(posed_position);
+        }
+        if min.cmple(max).all() {
+            *aabb = Aabb::from_min_max(min, max);
+        }
+    }
+}
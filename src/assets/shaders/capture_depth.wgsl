@@ -0,0 +1,15 @@
+#import bevy_pbr::{
+    forward_io::VertexOutput,
+    view_transformations::position_world_to_view,
+}
+
+// write the linear depth ( distance along the camera's viewing direction ) of the fragment, the 32 bits of the float are
+// split into 3 integers of 11 bits as a half float render target hold any integer up to 2048 exactly, the alpha tell
+// the model apart from the background
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    // the camera is looking toward -Z in view space
+    let depth = -position_world_to_view(in.world_position.xyz).z;
+    let bits = bitcast<u32>(depth);
+    return vec4<f32>(f32(bits & 2047u), f32((bits >> 11u) & 2047u), f32(bits >> 22u), 1.0);
+}
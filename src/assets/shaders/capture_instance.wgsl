@@ -0,0 +1,9 @@
+#import bevy_pbr::forward_io::VertexOutput
+
+@group(2) @binding(0) var<uniform> instance_id: u32;
+
+// write the instance id split into its low and high byte, so the value survive the half float render target
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(f32(instance_id & 255u), f32(instance_id >> 8u), 0.0, 1.0);
+}
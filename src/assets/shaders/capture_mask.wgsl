@@ -0,0 +1,7 @@
+#import bevy_pbr::forward_io::VertexOutput
+
+// every fragment of the model is foreground
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0, 1.0, 1.0, 1.0);
+}
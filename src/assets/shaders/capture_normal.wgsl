@@ -0,0 +1,17 @@
+#import bevy_pbr::{
+    forward_io::VertexOutput,
+    view_transformations::direction_world_to_view,
+}
+
+// 0 for world space normals, 1 for camera space normals
+@group(2) @binding(0) var<uniform> camera_space: u32;
+
+// write the surface normal of the fragment remapped from [-1, 1] to [0, 1]
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    var normal = normalize(in.world_normal);
+    if camera_space == 1u {
+        normal = normalize(direction_world_to_view(normal));
+    }
+    return vec4<f32>(normal * 0.5 + 0.5, 1.0);
+}
@@ -0,0 +1,45 @@
+#import bevy_pbr::{
+    forward_io::VertexOutput,
+    mesh_view_bindings as view_bindings,
+    mesh_view_types,
+    shadows,
+}
+
+// how dark a fully shadowed fragment is, from 0 to 1
+@group(2) @binding(0) var<uniform> shadow_opacity: f32;
+
+// black with the share of the directional light that doesn't reach the fragment as alpha, every light is weighted by
+// how much light it bring to the ground
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    let normal = normalize(in.world_normal);
+    let view_z = dot(vec4<f32>(
+        view_bindings::view.view_from_world[0].z,
+        view_bindings::view.view_from_world[1].z,
+        view_bindings::view.view_from_world[2].z,
+        view_bindings::view.view_from_world[3].z
+    ), in.world_position);
+
+    var total_light: f32 = 0.0;
+    var shadowed_light: f32 = 0.0;
+    for (var i: u32 = 0u; i < view_bindings::lights.n_directional_lights; i = i + 1u) {
+        let light = &view_bindings::lights.directional_lights[i];
+        if (*light).skip != 0u {
+            continue;
+        }
+        if ((*light).flags & mesh_view_types::DIRECTIONAL_LIGHT_FLAGS_SHADOWS_ENABLED_BIT) == 0u {
+            continue;
+        }
+
+        let light_amount = max(dot(normal, (*light).direction_to_light), 0.0)
+            * dot((*light).color.rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+        let shadow = shadows::fetch_directional_shadow(i, in.world_position, normal, view_z);
+        total_light += light_amount;
+        shadowed_light += light_amount * (1.0 - shadow);
+    }
+
+    if total_light <= 0.0 {
+        return vec4<f32>(0.0, 0.0, 0.0, 0.0);
+    }
+    return vec4<f32>(0.0, 0.0, 0.0, shadow_opacity * shadowed_light / total_light);
+}
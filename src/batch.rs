@@ -0,0 +1,274 @@
+use std::path::Path;
+
+use bevy::{asset::LoadState, core_pipeline::Skybox, prelude::*, window::PrimaryWindow};
+
+use crate::{
+    components::{CaptureProgressLabel, OrbitCamera},
+    export::read_capture_progress,
+    render::{interactive, switch_model},
+    resource::{
+        AssetPath, BatchCapture, CaptureManifestRecord, LightingRigSettings,
+        LiveCaptureOperationSettings, ModelAnimation, OperationSettings,
+        OperationWindowRelatedEntities, SavePathList, SkyboxAttribute,
+    },
+    states::{
+        AppState, BatchCaptureStage, CameraFovInitializedState, OperationState,
+        RenderModelForwardOrBackward,
+    },
+    utils::{resume_live_capture, start_live_capture},
+};
+
+// load the skybox given from the command line, the same way as dropping it on the main menu
+pub fn batch_capture_init(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    asset_path: Res<AssetPath>,
+) {
+    let skybox_handle: Handle<Image> = asset_server.load(asset_path.skybox_path.clone());
+    commands.insert_resource(SkyboxAttribute {
+        skybox_handler: Some(skybox_handle),
+        skybox_path: asset_path.skybox_path.clone(),
+    });
+}
+
+// open the operation window for the first model once the skybox is ready, the same way as clicking "Render Model"
+pub fn batch_open_operation_window(
+    commands: Commands,
+    asset_server: Res<AssetServer>,
+    asset_path: Res<AssetPath>,
+    images: ResMut<Assets<Image>>,
+    skybox_attributes: Res<SkyboxAttribute>,
+    operation_window: ResMut<OperationWindowRelatedEntities>,
+    operation_settings: Res<OperationSettings>,
+    lighting_rig: Res<LightingRigSettings>,
+    mut batch_capture: ResMut<BatchCapture>,
+    mut app_state: ResMut<NextState<AppState>>,
+    mut operation_state: ResMut<NextState<OperationState>>,
+    mut app_exit: EventWriter<AppExit>,
+) {
+    match batch_capture.stage {
+        BatchCaptureStage::OpenOperationWindow => {}
+        BatchCaptureStage::Finished | BatchCaptureStage::Cancelled => {
+            return;
+        }
+        // we are back to the main menu after the operation window was opened, meaning it was closed before the batch finished
+        _ => {
+            eprintln!("❌ Operation window closed before the batch capture finished");
+            cancel_batch_capture(&mut batch_capture, &mut app_exit);
+            return;
+        }
+    }
+
+    let skybox_handler = skybox_attributes.skybox_handler.as_ref().unwrap();
+    if let LoadState::Failed(err) = asset_server.load_state(skybox_handler) {
+        eprintln!(
+            "❌ Failed to load skybox {}: {}",
+            asset_path.skybox_path, err
+        );
+        cancel_batch_capture(&mut batch_capture, &mut app_exit);
+        return;
+    }
+
+    if asset_server.is_loaded(skybox_handler) {
+        println!("Enter Opration Mode 👷‍♂️");
+        interactive(
+            commands,
+            asset_server,
+            asset_path.models_path[asset_path.current_model_path_count as usize].clone(),
+            images,
+            skybox_attributes,
+            operation_window,
+            operation_settings,
+            lighting_rig,
+        );
+        app_state.set(AppState::OperationMode);
+        operation_state.set(OperationState::Interactive);
+        batch_capture.stage = BatchCaptureStage::StartCapture;
+    }
+}
+
+// exit with an error if the current model can't be loaded, otherwise the camera fov will never be initialized
+pub fn batch_check_model_load(
+    asset_server: Res<AssetServer>,
+    asset_path: Res<AssetPath>,
+    operation_window: Res<OperationWindowRelatedEntities>,
+    mut batch_capture: ResMut<BatchCapture>,
+    mut app_exit: EventWriter<AppExit>,
+) {
+    if batch_capture.stage != BatchCaptureStage::StartCapture {
+        return;
+    }
+    if let Some(scene_handler) = operation_window.current_scene_handler.as_ref() {
+        if let LoadState::Failed(err) = asset_server.load_state(scene_handler) {
+            eprintln!(
+                "❌ Failed to load model {}: {}",
+                asset_path.models_path[asset_path.current_model_path_count as usize], err
+            );
+            cancel_batch_capture(&mut batch_capture, &mut app_exit);
+        }
+    }
+}
+
+// the command line batch exit with an error, the "Capture All" button only stop and leave the window as it is
+fn cancel_batch_capture(batch_capture: &mut BatchCapture, app_exit: &mut EventWriter<AppExit>) {
+    if batch_capture.exit_on_finish {
+        app_exit.send(AppExit::error());
+    }
+    batch_capture.stage = BatchCaptureStage::Cancelled;
+}
+
+// start the live capture once the model fits in frame, and move on to the next model ( or exit ) once it is done
+pub fn batch_capture_progress(
+    commands: Commands,
+    asset_server: Res<AssetServer>,
+    operation_window: ResMut<OperationWindowRelatedEntities>,
+    operation_settings: Res<OperationSettings>,
+    mut live_capture_settings: ResMut<LiveCaptureOperationSettings>,
+    save_settings: ResMut<SavePathList>,
+    mut capture_manifest: ResMut<CaptureManifestRecord>,
+    assets_path: ResMut<AssetPath>,
+    mut batch_capture: ResMut<BatchCapture>,
+    mut operation_state: ResMut<NextState<OperationState>>,
+    mut camera_init_state: ResMut<NextState<CameraFovInitializedState>>,
+    mut window_query: Query<&mut Window, Without<PrimaryWindow>>,
+    mut app_exit: EventWriter<AppExit>,
+    skybox_attributes: Res<SkyboxAttribute>,
+    cameras: Query<(&Skybox, &OrbitCamera)>,
+    mut model_animation: ResMut<ModelAnimation>,
+) {
+    match batch_capture.stage {
+        BatchCaptureStage::StartCapture => {
+            // wait for the skybox of the model ( from the capture job ) to be shown
+            if cameras
+                .iter()
+                .any(|(skybox, _)| Some(&skybox.image) != skybox_attributes.skybox_handler.as_ref())
+            {
+                return;
+            }
+            // and for the animation clips of the model to be known
+            if live_capture_settings.animation_capture.is_some() && !model_animation.loaded {
+                return;
+            }
+
+            println!(
+                "start live capturing 🎥 [model {}/{}]",
+                assets_path.current_model_path_count + 1,
+                assets_path.models_path.len()
+            );
+            // running the same batch again continue the interrupted models and skip the finished ones
+            let current_dir_path = &save_settings.save_path_list
+                [save_settings.current_path_count as usize]
+                .current_dir_path;
+            match read_capture_progress(Path::new(current_dir_path)) {
+                Some(progress) if progress.current_counter >= progress.coordinate_list.len() => {
+                    println!("✅ Already captured, skipping");
+                }
+                Some(_) => {
+                    resume_live_capture(
+                        &mut live_capture_settings,
+                        &save_settings,
+                        &mut capture_manifest,
+                        &mut window_query,
+                        &mut model_animation,
+                    );
+                    operation_state.set(OperationState::LiveCapture);
+                }
+                None => {
+                    let current_viewpoint = match cameras.get_single() {
+                        Ok((_, orbit)) => (orbit.yaw, orbit.pitch, orbit.radius),
+                        Err(_) => (0.0, 0.0, operation_settings.radius_start_position),
+                    };
+                    start_live_capture(
+                        &operation_settings,
+                        &mut live_capture_settings,
+                        &save_settings,
+                        &mut capture_manifest,
+                        &mut window_query,
+                        assets_path.model_overrides.get(
+                            &assets_path.models_path[assets_path.current_model_path_count as usize],
+                        ),
+                        &mut model_animation,
+                        current_viewpoint,
+                    );
+                    operation_state.set(OperationState::LiveCapture);
+                }
+            }
+            batch_capture.stage = BatchCaptureStage::Capturing;
+        }
+        BatchCaptureStage::Capturing => {
+            // live capture will switch back to interactive mode once it reach the end of the coordinates list
+            if assets_path.current_model_path_count + 1 >= assets_path.models_path.len() as i64 {
+                println!("✅ Batch capture finished");
+                if batch_capture.exit_on_finish {
+                    app_exit.send(AppExit::Success);
+                }
+                batch_capture.stage = BatchCaptureStage::Finished;
+                return;
+            }
+
+            println!("🔄 Switching Model");
+            camera_init_state.set(CameraFovInitializedState::NotInitialized);
+            switch_model(
+                commands,
+                asset_server,
+                operation_window,
+                save_settings,
+                assets_path,
+                RenderModelForwardOrBackward::Forward,
+            );
+            batch_capture.stage = BatchCaptureStage::StartCapture;
+        }
+        BatchCaptureStage::OpenOperationWindow
+        | BatchCaptureStage::Finished
+        | BatchCaptureStage::Cancelled => {}
+    }
+}
+
+// show which model and view is being captured in the operation window title and on the main menu
+pub fn display_batch_capture_progress(
+    batch_capture: Res<BatchCapture>,
+    assets_path: Res<AssetPath>,
+    live_capture_settings: Res<LiveCaptureOperationSettings>,
+    current_operation_state: Res<State<OperationState>>,
+    mut window_query: Query<&mut Window, Without<PrimaryWindow>>,
+    mut progress_label: Query<&mut Text, With<CaptureProgressLabel>>,
+) {
+    let model_progress = format!(
+        "model {}/{}",
+        assets_path.current_model_path_count + 1,
+        assets_path.models_path.len()
+    );
+    let view_progress = format!(
+        "view {}/{}",
+        live_capture_settings.live_capture_iteration_current_counter,
+        live_capture_settings.live_capture_iteration
+    );
+    let progress = match batch_capture.stage {
+        BatchCaptureStage::OpenOperationWindow => "starting".to_string(),
+        BatchCaptureStage::StartCapture => format!("{} loading", model_progress),
+        BatchCaptureStage::Capturing => format!("{} {}", model_progress, view_progress),
+        BatchCaptureStage::Finished => {
+            format!("{} models captured ✅", assets_path.models_path.len())
+        }
+        BatchCaptureStage::Cancelled => format!("{} cancelled ❌", model_progress),
+    };
+
+    let label = format!("[ capture all ]: {}", progress);
+    for mut text in progress_label.iter_mut() {
+        if text.0 != label {
+            text.0 = label.clone();
+        }
+    }
+
+    // the live capture camera only show the view progress in the title
+    if batch_capture.stage == BatchCaptureStage::Capturing
+        && *current_operation_state.get() == OperationState::LiveCapture
+    {
+        let title = format!("Capture All 🎥 [{}] [{}]", model_progress, view_progress);
+        for mut window in window_query.iter_mut() {
+            if window.title != title {
+                window.title = title.clone();
+            }
+        }
+    }
+}
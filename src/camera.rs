@@ -1,4 +1,8 @@
+use std::path::Path;
+
 use bevy::{
+    asset::LoadState,
+    core_pipeline::Skybox,
     input::mouse::{MouseMotion, MouseWheel},
     prelude::*,
     render::primitives::Aabb,
@@ -6,11 +10,19 @@ use bevy::{
 };
 
 use crate::{
-    capture::take_snapshot,
-    components::{ModelRotateReposition, OrbitCamera},
+    animation::animation_frame_record,
+    capture::{model_bounds, snapshot_file_stem, take_snapshot},
+    components::{
+        CaptureChannelMesh, GroundPlane, ModelRotateReposition, OrbitCamera, RandomizedLight,
+    },
+    export::read_capture_progress,
+    ground::{spawn_ground_plane, GroundShadowMaterial},
+    lighting::{randomize_lighting, SceneLightFilter},
+    render::{pool_skybox_index, prepare_skybox_image},
     resource::{
-        LiveCameraPanNumber, LiveCaptureOperationSettings, OperationSettings,
-        OperationWindowRelatedEntities, SavePathList,
+        CaptureChannels, CaptureManifestRecord, DefaultLighting, LiveCameraPanNumber,
+        LiveCaptureOperationSettings, ModelAnimation, OperationSettings,
+        OperationWindowRelatedEntities, SavePathList, SkyboxPool, SnapshotsInFlight,
     },
     states::{CameraFovInitializedState, OperationState},
 };
@@ -128,20 +140,128 @@ pub fn live_orbit_camera(
     }
 }
 
-// camera that was control by the system that move to the coordinates that was calculated by fibonacci sphere and capture a screen shot
+// camera that was control by the system that move to the coordinates that was calculated by the viewpoint sampler and capture a screen shot
 // will end once it reach the end of the list and will switch to interactive mode once end
 pub fn live_capture_camera(
-    commands: Commands,
-    mut query: Query<(&mut Transform, &mut OrbitCamera)>,
+    mut commands: Commands,
+    mut query: Query<(&mut Transform, &mut OrbitCamera, &Projection, &mut Skybox)>,
     mut operation_state: ResMut<NextState<OperationState>>,
     operation_window: Res<OperationWindowRelatedEntities>,
     mut live_capture_settings: ResMut<LiveCaptureOperationSettings>,
     save_settings: Res<SavePathList>,
+    mut capture_manifest: ResMut<CaptureManifestRecord>,
+    capture_channels: Res<CaptureChannels>,
     mut window_query: Query<&mut Window, Without<PrimaryWindow>>,
+    meshes: Query<
+        (&GlobalTransform, &Aabb),
+        (
+            With<Mesh3d>,
+            Without<CaptureChannelMesh>,
+            Without<GroundPlane>,
+        ),
+    >,
+    models: Query<&GlobalTransform, With<ModelRotateReposition>>,
+    mut snapshots_in_flight: ResMut<SnapshotsInFlight>,
+    mut model_animation: ResMut<ModelAnimation>,
+    (mut ambient_light, mut default_lighting, mut lights, ground_planes): (
+        ResMut<AmbientLight>,
+        ResMut<DefaultLighting>,
+        Query<(Entity, &mut Visibility, Has<RandomizedLight>), SceneLightFilter>,
+        Query<(), With<GroundPlane>>,
+    ),
+    (asset_server, mut images, mut skybox_pool): (
+        Res<AssetServer>,
+        ResMut<Assets<Image>>,
+        ResMut<SkyboxPool>,
+    ),
 ) {
     let orbit_query = query.get_single_mut();
     match orbit_query {
-        Ok((mut transform, mut orbit)) => {
+        Ok((mut transform, mut orbit, projection, mut skybox)) => {
+            // the run only end once the screenshots of the last snapshots were saved
+            if live_capture_settings.live_capture_iteration_current_counter
+                >= live_capture_settings.live_capture_iteration
+            {
+                if snapshots_in_flight.pending_screenshots.is_empty() {
+                    finish_live_capture(&mut operation_state, &mut window_query);
+                }
+                return;
+            }
+            // wait for the screenshots before moving to the next viewpoint, so that no frame is dropped under load
+            if snapshots_in_flight.pending_screenshots.len()
+                >= live_capture_settings.max_snapshots_in_flight
+            {
+                return;
+            }
+
+            // a resumed run skip the viewpoints that were captured before the interruption
+            if live_capture_settings.skip_existing {
+                skip_captured_coordinates(&mut live_capture_settings, &save_settings);
+                if live_capture_settings.live_capture_iteration_current_counter
+                    >= live_capture_settings.live_capture_iteration
+                {
+                    // only the progress is left to be saved
+                    if let Some(progress) = capture_manifest.progress.as_mut() {
+                        progress.current_counter =
+                            live_capture_settings.live_capture_iteration_current_counter;
+                    }
+                    capture_manifest.pending_write = true;
+                    return;
+                }
+            }
+
+            // pose the model at the animation time of the snapshot first, the snapshot is taken on the next frame once
+            // the transforms of the model were updated
+            let animation_time = live_capture_settings
+                .live_capture_animation_times
+                .get(live_capture_settings.live_capture_iteration_current_counter)
+                .copied();
+            if let Some(animation_time) = animation_time {
+                if model_animation.time != animation_time || model_animation.playing {
+                    model_animation.playing = false;
+                    model_animation.time = animation_time;
+                    return;
+                }
+            }
+
+            // put the skybox of the frame in place, the snapshot is taken on the next frame once the image of a new
+            // skybox was prepared
+            let skybox_index = pool_skybox_index(
+                &skybox_pool,
+                live_capture_settings.live_capture_seed,
+                live_capture_settings.live_capture_iteration_current_counter,
+            );
+            if let Some(skybox_index) = skybox_index {
+                if skybox_pool.handlers.len() != skybox_pool.paths.len() {
+                    skybox_pool.handlers = skybox_pool
+                        .paths
+                        .iter()
+                        .map(|skybox_path| asset_server.load(skybox_path.clone()))
+                        .collect();
+                }
+                let skybox_handler = &skybox_pool.handlers[skybox_index];
+                if skybox.image != *skybox_handler {
+                    // the run end early on a skybox that can't be loaded, it can be resumed once the pool is fixed
+                    if let LoadState::Failed(err) = asset_server.load_state(skybox_handler) {
+                        eprintln!(
+                            "❌ unable to load skybox {}: {}",
+                            skybox_pool.paths[skybox_index], err
+                        );
+                        live_capture_settings.live_capture_iteration =
+                            live_capture_settings.live_capture_iteration_current_counter;
+                        return;
+                    }
+                    if !asset_server.is_loaded(skybox_handler) {
+                        return;
+                    }
+                    if let Some(image) = images.get_mut(skybox_handler) {
+                        prepare_skybox_image(image);
+                    }
+                    skybox.image = skybox_handler.clone();
+                    return;
+                }
+            }
+
             let current_coordinates = live_capture_settings.live_capture_coordinate_list
                 [live_capture_settings.live_capture_iteration_current_counter as usize];
             orbit.yaw = current_coordinates.0;
@@ -156,16 +276,48 @@ pub fn live_capture_camera(
             transform.translation = offset;
             transform.look_at(Vec3::ZERO, Vec3::Y);
 
+            let lighting = match &live_capture_settings.lighting_randomization {
+                Some(lighting_randomization) => Some(randomize_lighting(
+                    &mut commands,
+                    lighting_randomization,
+                    live_capture_settings.live_capture_seed,
+                    live_capture_settings.live_capture_iteration_current_counter,
+                    !ground_planes.is_empty(),
+                    &mut ambient_light,
+                    &mut default_lighting,
+                    &mut lights,
+                )),
+                None => None,
+            };
+
+            let window = window_query
+                .get(operation_window.window.unwrap())
+                .expect("window not found");
             take_snapshot(
                 commands,
                 operation_window,
                 &save_settings,
-                orbit.yaw,
-                orbit.pitch,
-                orbit.radius,
+                &mut capture_manifest,
+                &capture_channels,
+                &orbit,
+                &transform,
+                projection,
+                window,
+                &model_bounds(models.get_single().ok(), meshes.iter()),
+                &mut snapshots_in_flight,
+                match animation_time {
+                    Some(_) => animation_frame_record(&model_animation),
+                    None => None,
+                },
+                lighting,
+                skybox_index.map(|skybox_index| skybox_pool.paths[skybox_index].clone()),
             );
 
             live_capture_settings.live_capture_iteration_current_counter += 1;
+            if let Some(progress) = capture_manifest.progress.as_mut() {
+                progress.current_counter =
+                    live_capture_settings.live_capture_iteration_current_counter;
+            }
             for mut window in window_query.iter_mut() {
                 window.title = format!(
                     "Live Capturing 🎥 [{}/{}]",
@@ -173,14 +325,6 @@ pub fn live_capture_camera(
                     live_capture_settings.live_capture_iteration
                 );
             }
-            if live_capture_settings.live_capture_iteration_current_counter
-                >= live_capture_settings.live_capture_iteration
-            {
-                operation_state.set(OperationState::Interactive);
-                for mut window in window_query.iter_mut() {
-                    window.title = "Interactive 📱".to_string();
-                }
-            }
         }
         Err(_) => {
             return;
@@ -188,6 +332,52 @@ pub fn live_capture_camera(
     }
 }
 
+// move the counter past the viewpoints that already have a snapshot on disk
+fn skip_captured_coordinates(
+    live_capture_settings: &mut LiveCaptureOperationSettings,
+    save_settings: &SavePathList,
+) {
+    let current_save_path_info =
+        &save_settings.save_path_list[save_settings.current_path_count as usize];
+    while live_capture_settings.live_capture_iteration_current_counter
+        < live_capture_settings.live_capture_iteration
+    {
+        let (yaw, pitch, radius) = live_capture_settings.live_capture_coordinate_list
+            [live_capture_settings.live_capture_iteration_current_counter];
+        let animation_time = live_capture_settings
+            .live_capture_animation_times
+            .get(live_capture_settings.live_capture_iteration_current_counter)
+            .copied();
+        let file_name = format!(
+            "{}.jpg",
+            snapshot_file_stem(
+                &current_save_path_info.file_name_prefix,
+                yaw,
+                pitch,
+                radius,
+                animation_time
+            )
+        );
+        if !Path::new(&current_save_path_info.current_dir_path)
+            .join(file_name)
+            .exists()
+        {
+            return;
+        }
+        live_capture_settings.live_capture_iteration_current_counter += 1;
+    }
+}
+
+fn finish_live_capture(
+    operation_state: &mut NextState<OperationState>,
+    window_query: &mut Query<&mut Window, Without<PrimaryWindow>>,
+) {
+    operation_state.set(OperationState::Interactive);
+    for mut window in window_query.iter_mut() {
+        window.title = "Interactive 📱".to_string();
+    }
+}
+
 // initialized the camera fov so that the model fits nicely within frame before any operation
 pub fn initialized_camera_fov(
     mut query: Query<(&mut Transform, &mut OrbitCamera), Without<ModelRotateReposition>>,
@@ -196,9 +386,24 @@ pub fn initialized_camera_fov(
     operation_window: Res<OperationWindowRelatedEntities>,
     mut operation_settings: ResMut<OperationSettings>,
     mut camera_init_status: ResMut<NextState<CameraFovInitializedState>>,
-    meshes: Query<(&GlobalTransform, Option<&Aabb>), With<Mesh3d>>,
+    meshes: Query<
+        (&GlobalTransform, Option<&Aabb>),
+        (
+            With<Mesh3d>,
+            Without<CaptureChannelMesh>,
+            Without<GroundPlane>,
+        ),
+    >,
     children_query: Query<&Children>,
     window_query: Query<&mut Window, Without<PrimaryWindow>>,
+    save_settings: Res<SavePathList>,
+    (mut commands, mut mesh_assets, mut standard_materials, mut shadow_materials, ground_planes): (
+        Commands,
+        ResMut<Assets<Mesh>>,
+        ResMut<Assets<StandardMaterial>>,
+        ResMut<Assets<GroundShadowMaterial>>,
+        Query<Entity, With<GroundPlane>>,
+    ),
 ) {
     let orbit_query = query.get_single_mut();
 
@@ -222,9 +427,24 @@ pub fn initialized_camera_fov(
                         let mut final_distance: f32 = 0.0;
 
                         let mut point_to_move_downwards: f32 = 0.0;
+                        let mut model_bottom = f32::MAX;
                         for (global_trans, aabb) in meshes.iter() {
                             let aabb = aabb.unwrap();
 
+                            // the lowest corner of the bounding box in world space, for the ground plane
+                            for corner in 0..8 {
+                                let corner_sign = Vec3::new(
+                                    if corner & 1 == 0 { -1.0 } else { 1.0 },
+                                    if corner & 2 == 0 { -1.0 } else { 1.0 },
+                                    if corner & 4 == 0 { -1.0 } else { 1.0 },
+                                );
+                                let corner_position = global_trans.transform_point(
+                                    Vec3::from(aabb.center)
+                                        + Vec3::from(aabb.half_extents) * corner_sign,
+                                );
+                                model_bottom = model_bottom.min(corner_position.y);
+                            }
+
                             // get the center of the model to determine if the model's center is on the plane or above the plane
                             let center_of_model = aabb.center;
                             let scaled_center_of_model =
@@ -267,6 +487,25 @@ pub fn initialized_camera_fov(
                         // set the camera distance
                         camera_transform.translation = Vec3::new(0.0, 0.0, orbit.radius);
 
+                        // the ground plane of the previous model is replaced, the model is moved down along with its
+                        // bottom, from then on the plane follow the model ( see follow_model_with_ground_plane )
+                        for ground_plane in ground_planes.iter() {
+                            commands.entity(ground_plane).despawn();
+                        }
+                        if let Some(ground_plane) = operation_settings.ground_plane.as_ref() {
+                            if model_bottom < f32::MAX {
+                                spawn_ground_plane(
+                                    &mut commands,
+                                    &mut mesh_assets,
+                                    &mut standard_materials,
+                                    &mut shadow_materials,
+                                    ground_plane,
+                                    model_bottom - point_to_move_downwards,
+                                    final_distance * ground_plane.size,
+                                );
+                            }
+                        }
+
                         // set the operation settings based on scaling of the model
                         operation_settings.radius_range *= model_zoom_scale;
                         operation_settings.zoom_sensitivity *= model_zoom_scale;
@@ -274,6 +513,20 @@ pub fn initialized_camera_fov(
 
                         // set the camera initialized state
                         camera_init_status.set(CameraFovInitializedState::Initialized);
+
+                        // offer to resume if the last live capture of this model was interrupted
+                        let current_dir_path = &save_settings.save_path_list
+                            [save_settings.current_path_count as usize]
+                            .current_dir_path;
+                        if let Some(progress) = read_capture_progress(Path::new(current_dir_path)) {
+                            if progress.current_counter < progress.coordinate_list.len() {
+                                println!(
+                                    "💾 Unfinished live capture found [{}/{}], press R to resume it",
+                                    progress.current_counter,
+                                    progress.coordinate_list.len()
+                                );
+                            }
+                        }
                     }
                     Err(_) => {
                         return;
@@ -1,27 +1,167 @@
 use std::path::Path;
 
 use bevy::{
-    ecs::system::{Commands, Res},
-    render::view::screenshot::{save_to_disk, Screenshot},
+    core::FrameCount,
+    ecs::{
+        observer::Trigger,
+        system::{Commands, Res, ResMut},
+    },
+    math::{Mat3, Mat4, Vec2, Vec3},
+    render::{
+        camera::{PerspectiveProjection, Projection},
+        primitives::Aabb,
+        view::screenshot::{save_to_disk, Screenshot, ScreenshotCaptured},
+    },
+    transform::components::{GlobalTransform, Transform},
+    window::Window,
 };
 
-use crate::resource::{OperationWindowRelatedEntities, SavePathList};
+use crate::{
+    channels::{
+        take_depth_snapshot, take_instance_snapshot, take_mask_snapshot, take_normal_snapshot,
+        take_rgba_snapshot,
+    },
+    components::OrbitCamera,
+    export::{read_capture_manifest, write_yolo_label},
+    resource::{
+        CaptureChannels, CaptureManifestRecord, OperationWindowRelatedEntities, PendingSnapshot,
+        SavePathList, SnapshotsInFlight,
+    },
+    types::{AnimationFrameRecord, CameraIntrinsics, FrameRecord, LightingRecord, PoseRecord},
+};
+
+// the number of frames a snapshot can wait for its screenshots, a few seconds at 60 fps
+const SNAPSHOT_TIMEOUT_FRAMES: u32 = 300;
 
 // as the function name suggest, take a snapshot ( will be taking snapshot for the 3d model window )
 pub fn take_snapshot(
     mut commands: Commands,
     operation_window: Res<OperationWindowRelatedEntities>,
     save_settings: &SavePathList,
-    yaw: f32,
-    pitch: f32,
-    radius: f32,
+    capture_manifest: &mut CaptureManifestRecord,
+    capture_channels: &CaptureChannels,
+    orbit: &OrbitCamera,
+    camera_transform: &Transform,
+    projection: &Projection,
+    window: &Window,
+    model_bounds: &ModelBounds,
+    snapshots_in_flight: &mut SnapshotsInFlight,
+    animation: Option<AnimationFrameRecord>,
+    lighting: Option<LightingRecord>,
+    skybox: Option<String>,
 ) {
     let current_save_path_info =
         &save_settings.save_path_list[save_settings.current_path_count as usize];
-    let path = Path::new(&current_save_path_info.current_dir_path).join(format!(
-        "{}_{}_{}_{}.jpg",
-        current_save_path_info.file_name_prefix, yaw, pitch, radius
-    ));
+    let file_stem = snapshot_file_stem(
+        &current_save_path_info.file_name_prefix,
+        orbit.yaw,
+        orbit.pitch,
+        orbit.radius,
+        animation.as_ref().map(|animation| animation.time),
+    );
+    let file_name = format!("{}.jpg", file_stem);
+    let path = Path::new(&current_save_path_info.current_dir_path).join(&file_name);
+    let intrinsics = camera_intrinsics(projection, window);
+    let camera_to_world = camera_to_world(camera_transform);
+    let bbox = project_bounding_box(&model_bounds.world_corners, &camera_to_world, &intrinsics);
+    let pose = model_pose(model_bounds, &camera_to_world, &intrinsics);
+
+    let depth = take_depth_snapshot(
+        &mut commands,
+        capture_channels,
+        &current_save_path_info.current_dir_path,
+        &file_stem,
+        intrinsics.near,
+        intrinsics.far,
+        model_depth_range(&model_bounds.world_corners, &camera_to_world, &intrinsics),
+    );
+    let normal = take_normal_snapshot(
+        &mut commands,
+        capture_channels,
+        &current_save_path_info.current_dir_path,
+        &file_stem,
+    );
+    let mask_file_name = take_mask_snapshot(
+        &mut commands,
+        capture_channels,
+        &current_save_path_info.current_dir_path,
+        &file_stem,
+    );
+    let rgba_file_name = take_rgba_snapshot(
+        &mut commands,
+        capture_channels,
+        &current_save_path_info.current_dir_path,
+        &file_stem,
+    );
+    let instance_file_name = take_instance_snapshot(
+        &mut commands,
+        capture_channels,
+        &current_save_path_info.current_dir_path,
+        &file_stem,
+    );
+
+    // the window screenshot plus one per enabled channel
+    let screenshot_count = 1 + [
+        depth.is_some(),
+        normal.is_some(),
+        mask_file_name.is_some(),
+        rgba_file_name.is_some(),
+        instance_file_name.is_some(),
+    ]
+    .iter()
+    .filter(|enabled| **enabled)
+    .count();
+    let requested_frame = snapshots_in_flight.current_frame;
+    snapshots_in_flight
+        .pending_screenshots
+        .push_back(PendingSnapshot {
+            screenshots: screenshot_count,
+            requested_frame,
+        });
+
+    // record the exact camera used for this snapshot, continue the manifest of the directory if we are now saving to
+    // another model's directory so that the snapshots of the earlier sessions keep their metadata
+    if capture_manifest.dir_path != current_save_path_info.current_dir_path {
+        capture_manifest.dir_path = current_save_path_info.current_dir_path.clone();
+        capture_manifest.manifest =
+            read_capture_manifest(Path::new(&capture_manifest.dir_path)).unwrap_or_default();
+        capture_manifest.progress = None;
+    }
+    // one class per model, in the same order as the models were dropped
+    write_yolo_label(
+        &Path::new(&current_save_path_info.current_dir_path).join(format!("{}.txt", file_stem)),
+        save_settings.current_path_count as usize,
+        bbox,
+        &intrinsics,
+    );
+    capture_manifest.manifest.model_name = current_save_path_info.file_name_prefix.clone();
+    // a snapshot taken again from the same viewpoint replace the file, so it replace the record too
+    capture_manifest
+        .manifest
+        .frames
+        .retain(|frame| frame.file_name != file_name);
+    capture_manifest.manifest.frames.push(FrameRecord {
+        file_name,
+        yaw: orbit.yaw,
+        pitch: orbit.pitch,
+        radius: orbit.radius,
+        camera_to_world,
+        intrinsics,
+        bbox,
+        pose,
+        depth,
+        normal,
+        mask_file_name,
+        rgba_file_name,
+        instance_file_name,
+        animation,
+        lighting,
+        skybox,
+    });
+    if capture_channels.instance_image.is_some() {
+        capture_manifest.manifest.instances = capture_channels.instance_legend.clone();
+    }
+    capture_manifest.pending_write = true;
 
     // spawn the entity to capture snapshot of a window
     // NOTE: we need to tell the entity which window [Here it will be the window where the 3d model will be]
@@ -31,3 +171,260 @@ pub fn take_snapshot(
         ))
         .observe(save_to_disk(path));
 }
+
+// every output of a snapshot share this file stem, the animation time tell apart the frames taken from the same viewpoint
+pub fn snapshot_file_stem(
+    file_name_prefix: &str,
+    yaw: f32,
+    pitch: f32,
+    radius: f32,
+    animation_time: Option<f32>,
+) -> String {
+    return match animation_time {
+        Some(animation_time) => format!(
+            "{}_{}_{}_{}_t{}",
+            file_name_prefix, yaw, pitch, radius, animation_time
+        ),
+        None => format!("{}_{}_{}_{}", file_name_prefix, yaw, pitch, radius),
+    };
+}
+
+// count down the screenshots of the oldest snapshot in flight, it is done once all of them were saved
+pub fn screenshot_captured(
+    _trigger: Trigger<ScreenshotCaptured>,
+    mut snapshots_in_flight: ResMut<SnapshotsInFlight>,
+) {
+    if let Some(pending_snapshot) = snapshots_in_flight.pending_screenshots.front_mut() {
+        pending_snapshot.screenshots -= 1;
+        if pending_snapshot.screenshots == 0 {
+            snapshots_in_flight.pending_screenshots.pop_front();
+        }
+    }
+}
+
+// a screenshot can be lost ( minimized window, lost surface ), so a snapshot still waiting after SNAPSHOT_TIMEOUT_FRAMES
+// is given up instead of blocking the live capture forever, its frame is captured again when the run is resumed
+pub fn expire_snapshots_in_flight(
+    frame_count: Res<FrameCount>,
+    mut snapshots_in_flight: ResMut<SnapshotsInFlight>,
+) {
+    snapshots_in_flight.current_frame = frame_count.0;
+    // the oldest snapshot is always the first one to expire
+    while let Some(pending_snapshot) = snapshots_in_flight.pending_screenshots.front() {
+        let waited_frames = frame_count.0.wrapping_sub(pending_snapshot.requested_frame);
+        if waited_frames < SNAPSHOT_TIMEOUT_FRAMES {
+            return;
+        }
+        eprintln!(
+            "⚠️ {} screenshot(s) of a snapshot still missing after {} frames, giving up on it",
+            pending_snapshot.screenshots, waited_frames
+        );
+        snapshots_in_flight.pending_screenshots.pop_front();
+    }
+}
+
+// the camera transform carry a scale, only the rotation and translation are relevant for the pose
+pub fn camera_to_world(camera_transform: &Transform) -> [[f32; 4]; 4] {
+    return Mat4::from_rotation_translation(
+        camera_transform.rotation,
+        camera_transform.translation,
+    )
+    .transpose()
+    .to_cols_array_2d();
+}
+
+// the snapshot is taken at the window's physical resolution, so derive the pinhole intrinsics from it
+pub fn camera_intrinsics(projection: &Projection, window: &Window) -> CameraIntrinsics {
+    let perspective = match projection {
+        Projection::Perspective(perspective) => perspective.clone(),
+        // the operation window camera is always spawned with a perspective projection
+        _ => PerspectiveProjection::default(),
+    };
+    let width = window.physical_width();
+    let height = window.physical_height();
+    let focal_length = (height as f32 / 2.0) / (perspective.fov / 2.0).tan();
+
+    return CameraIntrinsics {
+        width,
+        height,
+        fov_y: perspective.fov,
+        aspect_ratio: width as f32 / height as f32,
+        near: perspective.near,
+        far: perspective.far,
+        fx: focal_length,
+        fy: focal_length,
+        cx: width as f32 / 2.0,
+        cy: height as f32 / 2.0,
+    };
+}
+
+// the bounds of the model currently rendered, gathered from the same Aabb as the camera fov initialization
+pub struct ModelBounds {
+    // the corners of every mesh's bounding box in world space
+    pub world_corners: Vec<Vec3>,
+    // the model's rotation and translation, None if the model isn't spawned
+    pub model_to_world: Option<Mat4>,
+    // the oriented bounding box of the whole model in model space
+    pub local_min: Vec3,
+    pub local_max: Vec3,
+}
+
+pub fn model_bounds<'a>(
+    model_transform: Option<&GlobalTransform>,
+    meshes: impl Iterator<Item = (&'a GlobalTransform, &'a Aabb)>,
+) -> ModelBounds {
+    let world_corners: Vec<Vec3> = meshes
+        .flat_map(|(global_trans, aabb)| {
+            box_corners(Vec3::from(aabb.min()), Vec3::from(aabb.max()))
+                .map(|corner| global_trans.transform_point(corner))
+        })
+        .collect();
+
+    // leave the model's scale out of the pose so that the rotation stay a pure rotation
+    let model_to_world = model_transform.map(|model_transform| {
+        let (_, rotation, translation) = model_transform.to_scale_rotation_translation();
+        Mat4::from_rotation_translation(rotation, translation)
+    });
+    let world_to_model = model_to_world.unwrap_or(Mat4::IDENTITY).inverse();
+    let (local_min, local_max) =
+        world_corners
+            .iter()
+            .fold((Vec3::MAX, Vec3::MIN), |(min, max), corner| {
+                let local_corner = world_to_model.transform_point3(*corner);
+                (min.min(local_corner), max.max(local_corner))
+            });
+
+    return ModelBounds {
+        world_corners,
+        model_to_world,
+        local_min,
+        local_max,
+    };
+}
+
+fn box_corners(min: Vec3, max: Vec3) -> [Vec3; 8] {
+    return [
+        Vec3::new(min.x, min.y, min.z),
+        Vec3::new(max.x, min.y, min.z),
+        Vec3::new(min.x, max.y, min.z),
+        Vec3::new(max.x, max.y, min.z),
+        Vec3::new(min.x, min.y, max.z),
+        Vec3::new(max.x, min.y, max.z),
+        Vec3::new(min.x, max.y, max.z),
+        Vec3::new(max.x, max.y, max.z),
+    ];
+}
+
+// project a world space point into pixel coordinates ( origin at the top left ), None if it is behind the camera
+pub fn project_point(
+    point: Vec3,
+    world_to_camera: &Mat4,
+    intrinsics: &CameraIntrinsics,
+) -> Option<Vec2> {
+    let camera_point = world_to_camera.transform_point3(point);
+    // the camera is looking toward -Z
+    if camera_point.z >= 0.0 {
+        return None;
+    }
+
+    return Some(Vec2::new(
+        intrinsics.cx + intrinsics.fx * camera_point.x / -camera_point.z,
+        intrinsics.cy - intrinsics.fy * camera_point.y / -camera_point.z,
+    ));
+}
+
+// the closest and farthest depth of the model's bounding box corners, kept between the near and far planes
+fn model_depth_range(
+    corners: &[Vec3],
+    camera_to_world: &[[f32; 4]; 4],
+    intrinsics: &CameraIntrinsics,
+) -> (f32, f32) {
+    let world_to_camera = Mat4::from_cols_array_2d(camera_to_world)
+        .transpose()
+        .inverse();
+    let (min_depth, max_depth) = corners
+        .iter()
+        .map(|corner| -world_to_camera.transform_point3(*corner).z)
+        .fold((f32::MAX, f32::MIN), |(min, max), depth| {
+            (min.min(depth), max.max(depth))
+        });
+    // without any mesh the whole frustum is used
+    if min_depth > max_depth {
+        return (intrinsics.near, intrinsics.far);
+    }
+
+    return (
+        min_depth.clamp(intrinsics.near, intrinsics.far),
+        max_depth.clamp(intrinsics.near, intrinsics.far),
+    );
+}
+
+// the [x, y, width, height] box in pixels enclosing the projected corners, clipped to the image
+fn project_bounding_box(
+    corners: &[Vec3],
+    camera_to_world: &[[f32; 4]; 4],
+    intrinsics: &CameraIntrinsics,
+) -> Option<[f32; 4]> {
+    let world_to_camera = Mat4::from_cols_array_2d(camera_to_world)
+        .transpose()
+        .inverse();
+    let projected: Vec<Vec2> = corners
+        .iter()
+        .filter_map(|corner| project_point(*corner, &world_to_camera, intrinsics))
+        .collect();
+    if projected.is_empty() {
+        return None;
+    }
+
+    let image_size = Vec2::new(intrinsics.width as f32, intrinsics.height as f32);
+    let min = projected
+        .iter()
+        .fold(Vec2::MAX, |min, point| min.min(*point))
+        .clamp(Vec2::ZERO, image_size);
+    let max = projected
+        .iter()
+        .fold(Vec2::MIN, |max, point| max.max(*point))
+        .clamp(Vec2::ZERO, image_size);
+    if max.x <= min.x || max.y <= min.y {
+        return None;
+    }
+
+    return Some([min.x, min.y, max.x - min.x, max.y - min.y]);
+}
+
+// the model to camera pose and the projected oriented bounding box of the model
+fn model_pose(
+    model_bounds: &ModelBounds,
+    camera_to_world: &[[f32; 4]; 4],
+    intrinsics: &CameraIntrinsics,
+) -> Option<PoseRecord> {
+    let model_to_world = model_bounds.model_to_world?;
+    if model_bounds.world_corners.is_empty() {
+        return None;
+    }
+
+    let world_to_camera = Mat4::from_cols_array_2d(camera_to_world)
+        .transpose()
+        .inverse();
+    // flip the Y and Z axis of bevy's camera to get the OpenCV convention
+    let model_to_camera =
+        Mat4::from_scale(Vec3::new(1.0, -1.0, -1.0)) * world_to_camera * model_to_world;
+    let bbox_3d = box_corners(model_bounds.local_min, model_bounds.local_max);
+
+    return Some(PoseRecord {
+        model_to_world: model_to_world.transpose().to_cols_array_2d(),
+        model_to_camera_rotation: Mat3::from_mat4(model_to_camera)
+            .transpose()
+            .to_cols_array_2d(),
+        model_to_camera_translation: model_to_camera.w_axis.truncate().to_array(),
+        bbox_3d: bbox_3d.map(|corner| corner.to_array()),
+        projected_bbox_3d: bbox_3d.map(|corner| {
+            project_point(
+                model_to_world.transform_point3(corner),
+                &world_to_camera,
+                intrinsics,
+            )
+            .map(|point| point.to_array())
+        }),
+    });
+}
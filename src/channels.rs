@@ -0,0 +1,794 @@
+use std::path::Path;
+
+use bevy::{
+    core_pipeline::{tonemapping::DebandDither, tonemapping::Tonemapping},
+    image::{Image, TextureFormatPixelInfo},
+    pbr::NotShadowCaster,
+    prelude::*,
+    render::{
+        camera::RenderTarget,
+        mesh::{morph::MeshMorphWeights, skinning::SkinnedMesh},
+        render_asset::RenderAssetUsages,
+        render_resource::{
+            AsBindGroup, Extent3d, ShaderRef, TextureDimension, TextureFormat, TextureUsages,
+        },
+        view::{
+            screenshot::{Screenshot, ScreenshotCaptured},
+            RenderLayers,
+        },
+    },
+    window::PrimaryWindow,
+};
+use image::{ImageBuffer, Luma, Rgb, Rgba};
+
+use crate::{
+    components::{CaptureChannelAttached, CaptureChannelCamera, CaptureChannelMesh, OrbitCamera},
+    resource::{CaptureChannels, OperationWindowRelatedEntities},
+    types::{DepthOutputFormat, DepthRecord, InstanceLegendEntry, NormalOutputSpace, NormalRecord},
+};
+
+// every capture channel render a copy of the model meshes on its own layer, so that the main camera never see them
+const DEPTH_RENDER_LAYER: usize = 1;
+const NORMAL_RENDER_LAYER: usize = 2;
+const MASK_RENDER_LAYER: usize = 3;
+const INSTANCE_RENDER_LAYER: usize = 4;
+
+// outputs the linear depth of the model, see assets/shaders/capture_depth.wgsl
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+pub struct CaptureDepthMaterial {}
+
+impl Material for CaptureDepthMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "embedded://mvc/assets/shaders/capture_depth.wgsl".into()
+    }
+}
+
+// outputs the world or camera space normals of the model, see assets/shaders/capture_normal.wgsl
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+pub struct CaptureNormalMaterial {
+    #[uniform(0)]
+    pub camera_space: u32,
+}
+
+impl Material for CaptureNormalMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "embedded://mvc/assets/shaders/capture_normal.wgsl".into()
+    }
+}
+
+// outputs white for every fragment of the model, see assets/shaders/capture_mask.wgsl
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+pub struct CaptureMaskMaterial {}
+
+impl Material for CaptureMaskMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "embedded://mvc/assets/shaders/capture_mask.wgsl".into()
+    }
+}
+
+// outputs the instance id of every model mesh, see assets/shaders/capture_instance.wgsl
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+pub struct CaptureInstanceMaterial {
+    #[uniform(0)]
+    pub instance_id: u32,
+}
+
+impl Material for CaptureInstanceMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "embedded://mvc/assets/shaders/capture_instance.wgsl".into()
+    }
+}
+
+pub fn init_capture_channels(
+    mut capture_channels: ResMut<CaptureChannels>,
+    mut depth_materials: ResMut<Assets<CaptureDepthMaterial>>,
+    mut normal_materials: ResMut<Assets<CaptureNormalMaterial>>,
+    mut mask_materials: ResMut<Assets<CaptureMaskMaterial>>,
+) {
+    capture_channels.mask_material = Some(mask_materials.add(CaptureMaskMaterial {}));
+    capture_channels.depth_material = Some(depth_materials.add(CaptureDepthMaterial {}));
+    capture_channels.normal_material =
+        Some(normal_materials.add(CaptureNormalMaterial { camera_space: 0 }));
+}
+
+// spawn a camera for every enabled channel as a child of the operation window camera, so it always share the same pose
+pub fn spawn_capture_channel_cameras(
+    mut commands: Commands,
+    query: Query<
+        (
+            Entity,
+            &OrbitCamera,
+            &Projection,
+            Option<&EnvironmentMapLight>,
+        ),
+        Added<OrbitCamera>,
+    >,
+    window_query: Query<&Window, Without<PrimaryWindow>>,
+    mut capture_channels: ResMut<CaptureChannels>,
+    mut images: ResMut<Assets<Image>>,
+    mut normal_materials: ResMut<Assets<CaptureNormalMaterial>>,
+) {
+    for (camera_entity, orbit, projection, environment_map_light) in query.iter() {
+        let size = match window_query.get(orbit.window) {
+            Ok(window) => Extent3d {
+                width: window.physical_width().max(1),
+                height: window.physical_height().max(1),
+                ..default()
+            },
+            Err(_) => continue,
+        };
+
+        capture_channels.depth_image = None;
+        if capture_channels.depth_output != DepthOutputFormat::None {
+            let depth_image = images.add(capture_channel_target(size));
+            spawn_capture_channel_camera(
+                &mut commands,
+                camera_entity,
+                &depth_image,
+                projection,
+                DEPTH_RENDER_LAYER,
+            );
+            capture_channels.depth_image = Some(depth_image);
+        }
+
+        capture_channels.normal_image = None;
+        if capture_channels.normal_output != NormalOutputSpace::None {
+            let normal_image = images.add(capture_channel_target(size));
+            spawn_capture_channel_camera(
+                &mut commands,
+                camera_entity,
+                &normal_image,
+                projection,
+                NORMAL_RENDER_LAYER,
+            );
+            capture_channels.normal_image = Some(normal_image);
+
+            // the normal space can be changed by dropping a new settings file
+            let camera_space = (capture_channels.normal_output == NormalOutputSpace::Camera) as u32;
+            let normal_material = capture_channels.normal_material.as_ref().unwrap();
+            normal_materials
+                .get_mut(normal_material)
+                .unwrap()
+                .camera_space = camera_space;
+        }
+
+        capture_channels.mask_image = None;
+        if capture_channels.mask_output {
+            let mask_image = images.add(capture_channel_target(size));
+            spawn_capture_channel_camera(
+                &mut commands,
+                camera_entity,
+                &mask_image,
+                projection,
+                MASK_RENDER_LAYER,
+            );
+            capture_channels.mask_image = Some(mask_image);
+        }
+
+        capture_channels.instance_image = None;
+        if capture_channels.instance_output {
+            let instance_image = images.add(capture_channel_target(size));
+            spawn_capture_channel_camera(
+                &mut commands,
+                camera_entity,
+                &instance_image,
+                projection,
+                INSTANCE_RENDER_LAYER,
+            );
+            capture_channels.instance_image = Some(instance_image);
+        }
+
+        // unlike the other channels, the RGBA channel render the model itself the same way as the operation window
+        // camera, only without the skybox
+        capture_channels.rgba_image = None;
+        if capture_channels.rgba_output {
+            let rgba_image = images.add(capture_channel_target(size));
+            let mut rgba_camera = commands.spawn((
+                Camera3d::default(),
+                Camera {
+                    target: RenderTarget::Image(rgba_image.clone()),
+                    hdr: true,
+                    // the skybox is replaced by a transparent background
+                    clear_color: ClearColorConfig::Custom(Color::NONE),
+                    ..default()
+                },
+                projection.clone(),
+                CaptureChannelCamera,
+            ));
+            if let Some(environment_map_light) = environment_map_light {
+                rgba_camera.insert(environment_map_light.clone());
+            }
+            rgba_camera.set_parent(camera_entity);
+            capture_channels.rgba_image = Some(rgba_image);
+        }
+    }
+}
+
+fn spawn_capture_channel_camera(
+    commands: &mut Commands,
+    camera_entity: Entity,
+    image: &Handle<Image>,
+    projection: &Projection,
+    render_layer: usize,
+) {
+    commands.entity(camera_entity).with_child((
+        Camera3d::default(),
+        Camera {
+            target: RenderTarget::Image(image.clone()),
+            // the values are kept unclamped, the ones that need more than the precision of a half float are split
+            // across the channels by the shaders
+            hdr: true,
+            // the background will be 0 in every channel
+            clear_color: ClearColorConfig::Custom(Color::NONE),
+            ..default()
+        },
+        projection.clone(),
+        // the rendered values are data, not colors, so they must not be altered in any way
+        Tonemapping::None,
+        DebandDither::Disabled,
+        Msaa::Off,
+        RenderLayers::layer(render_layer),
+        CaptureChannelCamera,
+    ));
+}
+
+// attach a copy of every newly spawned model mesh for each enabled channel
+pub fn attach_capture_channel_meshes(
+    mut commands: Commands,
+    mut capture_channels: ResMut<CaptureChannels>,
+    operation_window: Res<OperationWindowRelatedEntities>,
+    meshes: Query<
+        (
+            Entity,
+            &Mesh3d,
+            Option<&SkinnedMesh>,
+            Option<&MeshMorphWeights>,
+        ),
+        (Without<CaptureChannelMesh>, Without<CaptureChannelAttached>),
+    >,
+    parent_query: Query<&Parent>,
+    name_query: Query<&Name>,
+    mut instance_materials: ResMut<Assets<CaptureInstanceMaterial>>,
+) {
+    let scene_entity = match operation_window.current_scene_entity {
+        Some(scene_entity) => scene_entity,
+        None => return,
+    };
+
+    // the instance ids start again from 1 for every model
+    if capture_channels.instance_scene_entity != Some(scene_entity) {
+        capture_channels.instance_scene_entity = Some(scene_entity);
+        capture_channels.instance_legend = vec![];
+    }
+
+    // only the meshes of the current model, sorted by their path in the glTF hierarchy so that the instance ids are the
+    // same every time the model is loaded
+    let mut model_meshes: Vec<(
+        String,
+        Entity,
+        &Mesh3d,
+        Option<&SkinnedMesh>,
+        Option<&MeshMorphWeights>,
+    )> = meshes
+        .iter()
+        .filter(|(mesh_entity, _, _, _)| {
+            parent_query
+                .iter_ancestors(*mesh_entity)
+                .any(|ancestor| ancestor == scene_entity)
+        })
+        .map(|(mesh_entity, mesh, skinned_mesh, morph_weights)| {
+            let mut path: Vec<String> = std::iter::once(mesh_entity)
+                .chain(
+                    parent_query
+                        .iter_ancestors(mesh_entity)
+                        .take_while(|ancestor| *ancestor != scene_entity),
+                )
+                .map(|entity| match name_query.get(entity) {
+                    Ok(name) => name.to_string(),
+                    Err(_) => "".to_string(),
+                })
+                .collect();
+            path.reverse();
+            (
+                path.join("/"),
+                mesh_entity,
+                mesh,
+                skinned_mesh,
+                morph_weights,
+            )
+        })
+        .collect();
+    model_meshes.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.cmp(&b.1)));
+
+    for (_, mesh_entity, mesh, skinned_mesh, morph_weights) in model_meshes {
+        if capture_channels.depth_image.is_some() {
+            spawn_capture_channel_mesh(
+                &mut commands,
+                mesh_entity,
+                mesh,
+                skinned_mesh,
+                morph_weights,
+                capture_channels.depth_material.clone().unwrap(),
+                DEPTH_RENDER_LAYER,
+            );
+        }
+        if capture_channels.normal_image.is_some() {
+            spawn_capture_channel_mesh(
+                &mut commands,
+                mesh_entity,
+                mesh,
+                skinned_mesh,
+                morph_weights,
+                capture_channels.normal_material.clone().unwrap(),
+                NORMAL_RENDER_LAYER,
+            );
+        }
+        if capture_channels.mask_image.is_some() {
+            spawn_capture_channel_mesh(
+                &mut commands,
+                mesh_entity,
+                mesh,
+                skinned_mesh,
+                morph_weights,
+                capture_channels.mask_material.clone().unwrap(),
+                MASK_RENDER_LAYER,
+            );
+        }
+        if capture_channels.instance_image.is_some() {
+            // glTF primitives are spawned with the mesh name as a child of their node
+            let instance_id = capture_channels.instance_legend.len() as u32 + 1;
+            let mesh_name = match name_query.get(mesh_entity) {
+                Ok(name) => name.to_string(),
+                Err(_) => "".to_string(),
+            };
+            let node_name = match parent_query.get(mesh_entity) {
+                Ok(parent) => match name_query.get(parent.get()) {
+                    Ok(name) => name.to_string(),
+                    Err(_) => "".to_string(),
+                },
+                Err(_) => "".to_string(),
+            };
+            capture_channels.instance_legend.push(InstanceLegendEntry {
+                id: instance_id,
+                node_name,
+                mesh_name,
+            });
+
+            spawn_capture_channel_mesh(
+                &mut commands,
+                mesh_entity,
+                mesh,
+                skinned_mesh,
+                morph_weights,
+                instance_materials.add(CaptureInstanceMaterial { instance_id }),
+                INSTANCE_RENDER_LAYER,
+            );
+        }
+        commands.entity(mesh_entity).insert(CaptureChannelAttached);
+    }
+}
+
+// a copy of a model mesh for a capture channel, a skinned or morphed mesh get the same joints and weights so that the
+// copy is posed the same way as the model mesh during an animation
+fn spawn_capture_channel_mesh<M: Material>(
+    commands: &mut Commands,
+    mesh_entity: Entity,
+    mesh: &Mesh3d,
+    skinned_mesh: Option<&SkinnedMesh>,
+    morph_weights: Option<&MeshMorphWeights>,
+    material: Handle<M>,
+    render_layer: usize,
+) {
+    let mut channel_mesh = commands.spawn((
+        Mesh3d(mesh.0.clone()),
+        MeshMaterial3d(material),
+        RenderLayers::layer(render_layer),
+        NotShadowCaster,
+        CaptureChannelMesh,
+    ));
+    if let Some(skinned_mesh) = skinned_mesh {
+        channel_mesh.insert(skinned_mesh.clone());
+    }
+    if let Some(morph_weights) = morph_weights {
+        channel_mesh.insert(morph_weights.clone());
+    }
+    channel_mesh.set_parent(mesh_entity);
+}
+
+// the morph weights of a model mesh are set by the animation every frame, bevy only pass them to the direct children of
+// the animated node so the capture channel copies are updated here
+pub fn sync_capture_channel_morph_weights(
+    model_meshes: Query<
+        (&MeshMorphWeights, &Children),
+        (Without<CaptureChannelMesh>, Changed<MeshMorphWeights>),
+    >,
+    mut channel_meshes: Query<&mut MeshMorphWeights, With<CaptureChannelMesh>>,
+) {
+    for (morph_weights, children) in model_meshes.iter() {
+        let mut channel_mesh_iter = channel_meshes.iter_many_mut(children);
+        while let Some(mut channel_morph_weights) = channel_mesh_iter.fetch_next() {
+            channel_morph_weights.clear_weights();
+            channel_morph_weights.extend_weights(morph_weights.weights());
+        }
+    }
+}
+
+// keep the channel images at the same resolution as the operation window, so every channel line up pixel by pixel
+pub fn resize_capture_channel_targets(
+    capture_channels: Res<CaptureChannels>,
+    operation_window: Res<OperationWindowRelatedEntities>,
+    window_query: Query<&Window, Without<PrimaryWindow>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let window = match window_query.get(operation_window.window.unwrap()) {
+        Ok(window) => window,
+        Err(_) => return,
+    };
+    let size = Extent3d {
+        width: window.physical_width().max(1),
+        height: window.physical_height().max(1),
+        ..default()
+    };
+
+    for image_handle in [
+        capture_channels.depth_image.as_ref(),
+        capture_channels.normal_image.as_ref(),
+        capture_channels.mask_image.as_ref(),
+        capture_channels.rgba_image.as_ref(),
+        capture_channels.instance_image.as_ref(),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        let resize_needed = match images.get(image_handle) {
+            Some(image) => image.texture_descriptor.size != size,
+            None => false,
+        };
+        if resize_needed {
+            images.get_mut(image_handle).unwrap().resize(size);
+        }
+    }
+}
+
+// capture the depth image alongside the RGB snapshot, both screenshot are taken from the same rendered frame, the png16
+// values are spread over the depth range of the model
+pub fn take_depth_snapshot(
+    commands: &mut Commands,
+    capture_channels: &CaptureChannels,
+    dir_path: &str,
+    file_stem: &str,
+    near: f32,
+    far: f32,
+    (min_depth, max_depth): (f32, f32),
+) -> Option<DepthRecord> {
+    let depth_image = capture_channels.depth_image.as_ref()?;
+    let format = capture_channels.depth_output;
+    let file_name = match format {
+        DepthOutputFormat::Png16 => format!("{}_depth.png", file_stem),
+        DepthOutputFormat::Exr => format!("{}_depth.exr", file_stem),
+        DepthOutputFormat::None => return None,
+    };
+    let path = Path::new(dir_path).join(&file_name);
+
+    commands
+        .spawn(Screenshot::image(depth_image.clone()))
+        .observe(move |trigger: Trigger<ScreenshotCaptured>| {
+            save_depth_to_disk(&trigger.event().0, &path, format, min_depth, max_depth);
+        });
+
+    return Some(DepthRecord {
+        file_name,
+        format,
+        near,
+        far,
+        min_depth,
+        max_depth,
+        precision: depth_precision(format, min_depth, max_depth),
+    });
+}
+
+// the exr keep the 32 bit float rendered by the shader, png16 quantize the depth range of the model into 65534 steps
+fn depth_precision(format: DepthOutputFormat, min_depth: f32, max_depth: f32) -> f32 {
+    return match format {
+        DepthOutputFormat::Png16 => (max_depth - min_depth) / (u16::MAX - 1) as f32,
+        DepthOutputFormat::Exr | DepthOutputFormat::None => {
+            2.0_f32.powi(max_depth.abs().max(f32::MIN_POSITIVE).log2().floor() as i32 - 23)
+        }
+    };
+}
+
+// join the 3 integers of 11 bits written by assets/shaders/capture_depth.wgsl back into the depth, None for the
+// background
+fn decode_depth(pixel: &[f32; 4]) -> Option<f32> {
+    if pixel[3] < 0.5 {
+        return None;
+    }
+    let bits =
+        pixel[0].round() as u32 | (pixel[1].round() as u32) << 11 | (pixel[2].round() as u32) << 22;
+    return Some(f32::from_bits(bits));
+}
+
+// 0 is kept for the background, the depth range of the model is mapped to [1, 65535]
+fn png16_depth(depth: Option<f32>, min_depth: f32, max_depth: f32) -> u16 {
+    return match depth {
+        Some(depth) => {
+            let t =
+                ((depth - min_depth) / (max_depth - min_depth).max(f32::EPSILON)).clamp(0.0, 1.0);
+            1 + (t * (u16::MAX - 1) as f32).round() as u16
+        }
+        None => 0,
+    };
+}
+
+// capture the normal image alongside the RGB snapshot, saved as a PNG with the same name as the RGB snapshot
+pub fn take_normal_snapshot(
+    commands: &mut Commands,
+    capture_channels: &CaptureChannels,
+    dir_path: &str,
+    file_stem: &str,
+) -> Option<NormalRecord> {
+    let normal_image = capture_channels.normal_image.as_ref()?;
+    let file_name = format!("{}_normal.png", file_stem);
+    let path = Path::new(dir_path).join(&file_name);
+
+    commands
+        .spawn(Screenshot::image(normal_image.clone()))
+        .observe(move |trigger: Trigger<ScreenshotCaptured>| {
+            save_rgb_to_disk(&trigger.event().0, &path);
+        });
+
+    return Some(NormalRecord {
+        file_name,
+        space: capture_channels.normal_output,
+    });
+}
+
+// capture the foreground mask alongside the RGB snapshot, 255 for the model and 0 for the skybox
+pub fn take_mask_snapshot(
+    commands: &mut Commands,
+    capture_channels: &CaptureChannels,
+    dir_path: &str,
+    file_stem: &str,
+) -> Option<String> {
+    let mask_image = capture_channels.mask_image.as_ref()?;
+    let file_name = format!("{}_mask.png", file_stem);
+    let path = Path::new(dir_path).join(&file_name);
+
+    commands
+        .spawn(Screenshot::image(mask_image.clone()))
+        .observe(move |trigger: Trigger<ScreenshotCaptured>| {
+            let image = &trigger.event().0;
+            let pixels = float_pixels(image)
+                .iter()
+                .map(|pixel| if pixel[0] > 0.5 { u8::MAX } else { 0 })
+                .collect();
+            match ImageBuffer::<Luma<u8>, Vec<u8>>::from_raw(image.width(), image.height(), pixels)
+                .unwrap()
+                .save(&path)
+            {
+                Ok(_) => info!("Mask image saved to {}", path.display()),
+                Err(e) => error!("Cannot save mask image, IO error: {e}"),
+            }
+        });
+
+    return Some(file_name);
+}
+
+// capture the model over a transparent background alongside the RGB snapshot
+pub fn take_rgba_snapshot(
+    commands: &mut Commands,
+    capture_channels: &CaptureChannels,
+    dir_path: &str,
+    file_stem: &str,
+) -> Option<String> {
+    let rgba_image = capture_channels.rgba_image.as_ref()?;
+    let file_name = format!("{}_rgba.png", file_stem);
+    let path = Path::new(dir_path).join(&file_name);
+
+    commands
+        .spawn(Screenshot::image(rgba_image.clone()))
+        .observe(move |trigger: Trigger<ScreenshotCaptured>| {
+            let image = &trigger.event().0;
+            // the window surface is sRGB, so encode the linear colors the same way to match the RGB snapshot
+            let pixels = float_pixels(image)
+                .iter()
+                .flat_map(|pixel| {
+                    let color = LinearRgba::new(pixel[0], pixel[1], pixel[2], pixel[3]);
+                    Srgba::from(color).to_u8_array()
+                })
+                .collect();
+            match ImageBuffer::<Rgba<u8>, Vec<u8>>::from_raw(image.width(), image.height(), pixels)
+                .unwrap()
+                .save(&path)
+            {
+                Ok(_) => info!("RGBA image saved to {}", path.display()),
+                Err(e) => error!("Cannot save RGBA image, IO error: {e}"),
+            }
+        });
+
+    return Some(file_name);
+}
+
+// capture the instance id image alongside the RGB snapshot, as a 16 bit grayscale PNG where 0 is the background and
+// every other value is an id from the instance legend
+pub fn take_instance_snapshot(
+    commands: &mut Commands,
+    capture_channels: &CaptureChannels,
+    dir_path: &str,
+    file_stem: &str,
+) -> Option<String> {
+    let instance_image = capture_channels.instance_image.as_ref()?;
+    let file_name = format!("{}_instance.png", file_stem);
+    let path = Path::new(dir_path).join(&file_name);
+
+    commands
+        .spawn(Screenshot::image(instance_image.clone()))
+        .observe(move |trigger: Trigger<ScreenshotCaptured>| {
+            let image = &trigger.event().0;
+            let pixels = float_pixels(image)
+                .iter()
+                .map(|pixel| pixel[0].round() as u16 + pixel[1].round() as u16 * 256)
+                .collect();
+            match ImageBuffer::<Luma<u16>, Vec<u16>>::from_raw(
+                image.width(),
+                image.height(),
+                pixels,
+            )
+            .unwrap()
+            .save(&path)
+            {
+                Ok(_) => info!("Instance image saved to {}", path.display()),
+                Err(e) => error!("Cannot save instance image, IO error: {e}"),
+            }
+        });
+
+    return Some(file_name);
+}
+
+fn save_depth_to_disk(
+    image: &Image,
+    path: &Path,
+    format: DepthOutputFormat,
+    min_depth: f32,
+    max_depth: f32,
+) {
+    let width = image.width();
+    let height = image.height();
+    let depths: Vec<Option<f32>> = float_pixels(image).iter().map(decode_depth).collect();
+
+    let result = match format {
+        DepthOutputFormat::Png16 => {
+            let pixels = depths
+                .iter()
+                .map(|depth| png16_depth(*depth, min_depth, max_depth))
+                .collect();
+            ImageBuffer::<Luma<u16>, Vec<u16>>::from_raw(width, height, pixels)
+                .unwrap()
+                .save(path)
+        }
+        // the background is 0
+        DepthOutputFormat::Exr => {
+            let pixels = depths
+                .iter()
+                .flat_map(|depth| [depth.unwrap_or(0.0); 3])
+                .collect();
+            ImageBuffer::<Rgb<f32>, Vec<f32>>::from_raw(width, height, pixels)
+                .unwrap()
+                .save(path)
+        }
+        DepthOutputFormat::None => return,
+    };
+
+    match result {
+        Ok(_) => info!("Depth image saved to {}", path.display()),
+        Err(e) => error!("Cannot save depth image, IO error: {e}"),
+    }
+}
+
+// save the [0, 1] RGB values of a channel image as an 8 bit PNG
+fn save_rgb_to_disk(image: &Image, path: &Path) {
+    let pixels = float_pixels(image)
+        .iter()
+        .flat_map(|pixel| {
+            [pixel[0], pixel[1], pixel[2]]
+                .map(|value| (value.clamp(0.0, 1.0) * 255.0).round() as u8)
+        })
+        .collect();
+
+    match ImageBuffer::<Rgb<u8>, Vec<u8>>::from_raw(image.width(), image.height(), pixels)
+        .unwrap()
+        .save(path)
+    {
+        Ok(_) => info!("Channel image saved to {}", path.display()),
+        Err(e) => error!("Cannot save channel image, IO error: {e}"),
+    }
+}
+
+// every channel is copied into a Rgba32Float image, as the 8 bit formats would be gamma corrected when captured, the
+// values themselves only have the precision of the Rgba16Float main pass
+fn float_pixels(image: &Image) -> Vec<[f32; 4]> {
+    return image
+        .data
+        .chunks_exact(16)
+        .map(|pixel| {
+            [0, 4, 8, 12].map(|offset| {
+                f32::from_le_bytes([
+                    pixel[offset],
+                    pixel[offset + 1],
+                    pixel[offset + 2],
+                    pixel[offset + 3],
+                ])
+            })
+        })
+        .collect();
+}
+
+fn capture_channel_target(size: Extent3d) -> Image {
+    let format = TextureFormat::Rgba32Float;
+    let mut image = Image::new_fill(
+        size,
+        TextureDimension::D2,
+        &vec![0; format.pixel_size()],
+        format,
+        RenderAssetUsages::default(),
+    );
+    image.texture_descriptor.usage = TextureUsages::TEXTURE_BINDING
+        | TextureUsages::COPY_DST
+        | TextureUsages::COPY_SRC
+        | TextureUsages::RENDER_ATTACHMENT;
+
+    return image;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the same split as assets/shaders/capture_depth.wgsl
+    fn encode_depth(depth: f32) -> [f32; 4] {
+        let bits = depth.to_bits();
+        return [bits & 2047, (bits >> 11) & 2047, bits >> 22, 1].map(|value| value as f32);
+    }
+
+    #[test]
+    fn depth_keep_its_32_bits_through_the_half_float_target() {
+        for depth in [0.1, 0.123_456_79, 1.0, 2.000_000_2, 3.141_592_7, 999.999_94] {
+            let pixel = encode_depth(depth);
+            // a half float hold every integer up to 2048 exactly
+            assert!(pixel
+                .iter()
+                .all(|value| *value <= 2048.0 && value.fract() == 0.0));
+            assert_eq!(decode_depth(&pixel), Some(depth));
+        }
+    }
+
+    #[test]
+    fn background_has_no_depth() {
+        assert_eq!(decode_depth(&[0.0; 4]), None);
+        assert_eq!(png16_depth(None, 1.0, 2.0), 0);
+    }
+
+    #[test]
+    fn png16_depth_spread_over_the_model_range() {
+        assert_eq!(png16_depth(Some(1.0), 1.0, 2.0), 1);
+        assert_eq!(png16_depth(Some(1.5), 1.0, 2.0), 32768);
+        assert_eq!(png16_depth(Some(2.0), 1.0, 2.0), u16::MAX);
+        // the model bounds can be a little off, the values stay away from the background
+        assert_eq!(png16_depth(Some(0.5), 1.0, 2.0), 1);
+        assert_eq!(png16_depth(Some(3.0), 1.0, 2.0), u16::MAX);
+        // a flat model facing the camera
+        assert_eq!(png16_depth(Some(1.0), 1.0, 1.0), 1);
+    }
+
+    #[test]
+    fn depth_precision_of_the_formats() {
+        assert_eq!(
+            depth_precision(DepthOutputFormat::Png16, 1.0, 1.0 + 65534.0 / 1024.0),
+            1.0 / 1024.0
+        );
+        assert_eq!(
+            depth_precision(DepthOutputFormat::Exr, 1.0, 3.0),
+            2.0_f32.powi(-22)
+        );
+    }
+}
@@ -9,9 +9,15 @@ pub struct ClearModelAssetsButton;
 #[derive(Component)]
 pub struct ClearSkyboxAssetsButton;
 
+#[derive(Component)]
+pub struct CaptureAllButton;
+
 #[derive(Component)]
 pub struct ModelPathLabel;
 
+#[derive(Component)]
+pub struct CaptureProgressLabel;
+
 #[derive(Component)]
 pub struct SkyboxPathLabel;
 
@@ -30,3 +36,23 @@ pub struct ModelRotateReposition {
     pub x: f32,
     pub y: f32,
 }
+
+// the camera rendering one of the extra capture channel ( depth, ... ) of the operation window camera
+#[derive(Component)]
+pub struct CaptureChannelCamera;
+
+// a copy of a model mesh that is only visible to a capture channel camera
+#[derive(Component)]
+pub struct CaptureChannelMesh;
+
+// the model mesh already have its capture channel copies
+#[derive(Component)]
+pub struct CaptureChannelAttached;
+
+// a light spawned for a single live capture frame when the lighting is randomized
+#[derive(Component)]
+pub struct RandomizedLight;
+
+// the ground plane under the model, it is not part of the model so it is left out of its bounding box
+#[derive(Component)]
+pub struct GroundPlane;
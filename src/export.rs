@@ -0,0 +1,589 @@
+use std::{
+    collections::BTreeMap,
+    fs::{create_dir_all, write, File, OpenOptions},
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    resource::{CaptureManifestRecord, SavePathList},
+    types::{
+        BopGroundTruth, BopProjectedBox, BopSceneCamera, CameraIntrinsics, CaptureManifest,
+        CaptureProgress, CocoAnnotation, CocoCategory, CocoDataset, CocoImage, NerfFrame,
+        NerfTransforms,
+    },
+};
+
+const CAPTURE_MANIFEST_FILE_NAME: &str = "capture_manifest.json";
+const CAPTURE_PROGRESS_FILE_NAME: &str = "capture_progress.json";
+const NERF_TRANSFORMS_FILE_NAME: &str = "transforms.json";
+const COLMAP_SPARSE_DIR_NAME: &str = "sparse/0";
+const INSTANCE_LEGEND_FILE_NAME: &str = "instance_legend.json";
+const COCO_ANNOTATIONS_FILE_NAME: &str = "annotations.json";
+const YOLO_DATA_CONFIG_FILE_NAME: &str = "data.yaml";
+const YOLO_TRAIN_LIST_FILE_NAME: &str = "train.txt";
+const YOLO_VAL_LIST_FILE_NAME: &str = "val.txt";
+const YOLO_VAL_EVERY: usize = 5;
+const BOP_SCENE_CAMERA_FILE_NAME: &str = "scene_camera.json";
+const BOP_SCENE_GT_FILE_NAME: &str = "scene_gt.json";
+const BOP_SCENE_IMAGES_FILE_NAME: &str = "scene_images.json";
+const BOP_SCENE_PROJECTED_BBOX_3D_FILE_NAME: &str = "scene_projected_bbox_3d.json";
+
+// write the manifest of the capture run ( and the datasets derived from it ) into the model's save directory, only run
+// when not live capturing so that a run with thousands of snapshots doesn't rewrite the files on every frame
+pub fn write_capture_manifest(
+    mut capture_manifest: ResMut<CaptureManifestRecord>,
+    save_settings: Res<SavePathList>,
+) {
+    if !capture_manifest.pending_write {
+        return;
+    }
+    capture_manifest.pending_write = false;
+
+    let dir_path = Path::new(&capture_manifest.dir_path);
+    write_json_file(
+        &dir_path.join(CAPTURE_MANIFEST_FILE_NAME),
+        &capture_manifest.manifest,
+    );
+    if let Some(progress) = capture_manifest.progress.as_ref() {
+        write_capture_progress(dir_path, progress);
+    }
+    if let Some(nerf_transforms) = nerf_transforms(&capture_manifest.manifest) {
+        write_json_file(&dir_path.join(NERF_TRANSFORMS_FILE_NAME), &nerf_transforms);
+    }
+    write_colmap_model(
+        &dir_path.join(COLMAP_SPARSE_DIR_NAME),
+        &capture_manifest.manifest,
+    );
+    if !capture_manifest.manifest.frames.is_empty() {
+        write_json_file(
+            &dir_path.join(COCO_ANNOTATIONS_FILE_NAME),
+            &coco_dataset(&capture_manifest.manifest),
+        );
+    }
+    if let Some(scene) = bop_scene(&capture_manifest.manifest) {
+        write_json_file(
+            &dir_path.join(BOP_SCENE_CAMERA_FILE_NAME),
+            &scene.scene_camera,
+        );
+        write_json_file(&dir_path.join(BOP_SCENE_GT_FILE_NAME), &scene.scene_gt);
+        write_json_file(
+            &dir_path.join(BOP_SCENE_IMAGES_FILE_NAME),
+            &scene.scene_images,
+        );
+        write_json_file(
+            &dir_path.join(BOP_SCENE_PROJECTED_BBOX_3D_FILE_NAME),
+            &scene.scene_projected_bbox_3d,
+        );
+    }
+    if !capture_manifest.manifest.instances.is_empty() {
+        write_json_file(
+            &dir_path.join(INSTANCE_LEGEND_FILE_NAME),
+            &capture_manifest.manifest.instances,
+        );
+    }
+    // the image lists are built from the manifests on disk, this one included
+    write_yolo_data_config(&save_settings);
+}
+
+pub fn write_capture_progress(dir_path: &Path, progress: &CaptureProgress) {
+    write_json_file(&dir_path.join(CAPTURE_PROGRESS_FILE_NAME), progress);
+}
+
+pub fn read_capture_progress(dir_path: &Path) -> Option<CaptureProgress> {
+    return read_json_file(&dir_path.join(CAPTURE_PROGRESS_FILE_NAME));
+}
+
+pub fn read_capture_manifest(dir_path: &Path) -> Option<CaptureManifest> {
+    return read_json_file(&dir_path.join(CAPTURE_MANIFEST_FILE_NAME));
+}
+
+// NeRF tooling expect a single camera for the whole dataset, so the intrinsics of the first snapshot are used
+fn nerf_transforms(manifest: &CaptureManifest) -> Option<NerfTransforms> {
+    let intrinsics = &manifest.frames.first()?.intrinsics;
+
+    return Some(NerfTransforms {
+        camera_angle_x: 2.0 * (intrinsics.width as f32 / (2.0 * intrinsics.fx)).atan(),
+        camera_angle_y: intrinsics.fov_y,
+        fl_x: intrinsics.fx,
+        fl_y: intrinsics.fy,
+        cx: intrinsics.cx,
+        cy: intrinsics.cy,
+        w: intrinsics.width,
+        h: intrinsics.height,
+        frames: manifest
+            .frames
+            .iter()
+            .map(|frame| NerfFrame {
+                file_path: format!("./{}", frame.file_name),
+                transform_matrix: frame.camera_to_world,
+            })
+            .collect(),
+    });
+}
+
+// a single category named after the model file, snapshots where the model is out of frame have no annotation
+fn coco_dataset(manifest: &CaptureManifest) -> CocoDataset {
+    let mut images = vec![];
+    let mut annotations = vec![];
+    for (index, frame) in manifest.frames.iter().enumerate() {
+        images.push(CocoImage {
+            id: index + 1,
+            file_name: frame.file_name.clone(),
+            width: frame.intrinsics.width,
+            height: frame.intrinsics.height,
+        });
+        if let Some(bbox) = frame.bbox {
+            annotations.push(CocoAnnotation {
+                id: annotations.len() + 1,
+                image_id: index + 1,
+                category_id: 1,
+                bbox,
+                area: bbox[2] * bbox[3],
+                iscrowd: 0,
+            });
+        }
+    }
+
+    return CocoDataset {
+        images,
+        annotations,
+        categories: vec![CocoCategory {
+            id: 1,
+            name: manifest.model_name.clone(),
+        }],
+    };
+}
+
+// the files of BOP's scene layout, every one of them keyed by the image id ( the snapshot's index in the manifest )
+struct BopScene {
+    scene_camera: BTreeMap<usize, BopSceneCamera>,
+    scene_gt: BTreeMap<usize, Vec<BopGroundTruth>>,
+    // the snapshots keep their own names instead of BOP's rgb/{im_id:06}, so the image id is mapped to the file name
+    scene_images: BTreeMap<usize, String>,
+    scene_projected_bbox_3d: BTreeMap<usize, Vec<BopProjectedBox>>,
+}
+
+// the model is the only object with the id 1 and as glTF is in meters the translations are converted to millimeters,
+// the scale of the model isn't part of cam_R_m2c ( see BopGroundTruth )
+fn bop_scene(manifest: &CaptureManifest) -> Option<BopScene> {
+    if manifest.frames.iter().all(|frame| frame.pose.is_none()) {
+        return None;
+    }
+
+    let mut scene = BopScene {
+        scene_camera: BTreeMap::new(),
+        scene_gt: BTreeMap::new(),
+        scene_images: BTreeMap::new(),
+        scene_projected_bbox_3d: BTreeMap::new(),
+    };
+    for (index, frame) in manifest.frames.iter().enumerate() {
+        let intrinsics = &frame.intrinsics;
+        let world_to_camera = Mat4::from_scale(Vec3::new(1.0, -1.0, -1.0))
+            * Mat4::from_cols_array_2d(&frame.camera_to_world)
+                .transpose()
+                .inverse();
+        scene.scene_camera.insert(
+            index,
+            BopSceneCamera {
+                cam_k: [
+                    intrinsics.fx,
+                    0.0,
+                    intrinsics.cx,
+                    0.0,
+                    intrinsics.fy,
+                    intrinsics.cy,
+                    0.0,
+                    0.0,
+                    1.0,
+                ],
+                cam_r_w2c: Mat3::from_mat4(world_to_camera).transpose().to_cols_array(),
+                cam_t_w2c: (world_to_camera.w_axis.truncate() * 1000.0).to_array(),
+            },
+        );
+        scene.scene_images.insert(index, frame.file_name.clone());
+        let (ground_truth, projected_box) = match &frame.pose {
+            Some(pose) => (
+                vec![BopGroundTruth {
+                    cam_r_m2c: pose.model_to_camera_rotation.concat().try_into().unwrap(),
+                    cam_t_m2c: pose.model_to_camera_translation.map(|value| value * 1000.0),
+                    obj_id: 1,
+                }],
+                vec![BopProjectedBox {
+                    obj_id: 1,
+                    projected_bbox_3d: pose.projected_bbox_3d,
+                }],
+            ),
+            None => (vec![], vec![]),
+        };
+        scene.scene_gt.insert(index, ground_truth);
+        scene.scene_projected_bbox_3d.insert(index, projected_box);
+    }
+
+    return Some(scene);
+}
+
+// write the YOLO label of a snapshot as `class cx cy w h` normalized by the image size, an empty file mean the model is
+// out of frame and the snapshot is used as a background image
+pub fn write_yolo_label(
+    path: &Path,
+    class_index: usize,
+    bbox: Option<[f32; 4]>,
+    intrinsics: &CameraIntrinsics,
+) {
+    let content = match bbox {
+        Some([x, y, w, h]) => {
+            let image_width = intrinsics.width as f32;
+            let image_height = intrinsics.height as f32;
+            format!(
+                "{} {} {} {} {}\n",
+                class_index,
+                (x + w / 2.0) / image_width,
+                (y + h / 2.0) / image_height,
+                w / image_width,
+                h / image_height
+            )
+        }
+        None => String::new(),
+    };
+    if let Err(err) = write(path, content) {
+        eprintln!("❌ Failed to write {:?}: {}", path, err);
+    }
+}
+
+// write the YOLO dataset config into every output directory with the image lists of the training and validation sets,
+// the lists only hold the RGB snapshots of the manifests so that the channel images saved next to them aren't taken as
+// background images, the class names always list every model so that the class indices of the labels are the same
+// whatever the output directory
+pub fn write_yolo_data_config(save_settings: &SavePathList) {
+    let mut output_dir_paths: Vec<&str> = save_settings
+        .save_path_list
+        .iter()
+        .map(|save_path| save_path.output_dir_path.as_str())
+        .collect();
+    output_dir_paths.sort();
+    output_dir_paths.dedup();
+
+    let mut names = String::new();
+    for (index, save_path) in save_settings.save_path_list.iter().enumerate() {
+        names.push_str(&format!(
+            "  {}: \"{}\"\n",
+            index, save_path.file_name_prefix
+        ));
+    }
+
+    for output_dir_path in output_dir_paths {
+        let output_dir = Path::new(output_dir_path);
+        let manifests: Vec<(&Path, CaptureManifest)> = save_settings
+            .save_path_list
+            .iter()
+            .filter(|save_path| save_path.output_dir_path == output_dir_path)
+            .filter_map(|save_path| {
+                let dir_path = Path::new(&save_path.current_dir_path);
+                read_capture_manifest(dir_path).map(|manifest| (dir_path, manifest))
+            })
+            .collect();
+        let (train, val) = yolo_image_lists(output_dir, &manifests);
+        let content = format!(
+            "path: \"{}\"\ntrain: \"{}\"\nval: \"{}\"\nnc: {}\nnames:\n{}",
+            output_dir_path,
+            YOLO_TRAIN_LIST_FILE_NAME,
+            YOLO_VAL_LIST_FILE_NAME,
+            save_settings.save_path_list.len(),
+            names
+        );
+
+        if let Err(err) = create_dir_all(output_dir) {
+            eprintln!("❌ Failed to create {:?}: {}", output_dir, err);
+            continue;
+        }
+        for (file_name, content) in [
+            (YOLO_TRAIN_LIST_FILE_NAME, train.join("\n")),
+            (YOLO_VAL_LIST_FILE_NAME, val.join("\n")),
+            (YOLO_DATA_CONFIG_FILE_NAME, content),
+        ] {
+            let path = output_dir.join(file_name);
+            if let Err(err) = write(&path, content) {
+                eprintln!("❌ Failed to write {:?}: {}", path, err);
+            }
+        }
+    }
+}
+
+// split the snapshots of every model between the training and validation sets ( every YOLO_VAL_EVERY th snapshot is
+// kept for validation, a model with fewer snapshots give its last one ), the paths start with "./" so that they are
+// resolved from the directory of the list, the snapshots that never made it to the disk are left out
+fn yolo_image_lists(
+    output_dir: &Path,
+    manifests: &[(&Path, CaptureManifest)],
+) -> (Vec<String>, Vec<String>) {
+    let mut train = vec![];
+    let mut val = vec![];
+    for (dir_path, manifest) in manifests {
+        let image_paths: Vec<PathBuf> = manifest
+            .frames
+            .iter()
+            .map(|frame| dir_path.join(&frame.file_name))
+            .filter(|image_path| image_path.exists())
+            .collect();
+        let image_count = image_paths.len();
+        for (index, image_path) in image_paths.into_iter().enumerate() {
+            let list_path = format!(
+                "./{}",
+                image_path
+                    .strip_prefix(output_dir)
+                    .unwrap_or(&image_path)
+                    .to_string_lossy()
+                    .replace('\\', "/")
+            );
+            let is_val = (index + 1) % YOLO_VAL_EVERY == 0
+                || (image_count > 1 && image_count < YOLO_VAL_EVERY && index == image_count - 1);
+            if is_val {
+                val.push(list_path);
+            } else {
+                train.push(list_path);
+            }
+        }
+    }
+
+    return (train, val);
+}
+
+// write the poses into COLMAP's text model format so that the feature matching pose estimation can be skipped,
+// all the snapshots share a single PINHOLE camera and there is no 3d point as nothing was triangulated
+fn write_colmap_model(sparse_dir_path: &Path, manifest: &CaptureManifest) {
+    let intrinsics = match manifest.frames.first() {
+        Some(frame) => &frame.intrinsics,
+        None => return,
+    };
+
+    let cameras = format!(
+        "# Camera list with one line of data per camera:\n\
+         #   CAMERA_ID, MODEL, WIDTH, HEIGHT, PARAMS[]\n\
+         # Number of cameras: 1\n\
+         1 PINHOLE {} {} {} {} {} {}\n",
+        intrinsics.width,
+        intrinsics.height,
+        intrinsics.fx,
+        intrinsics.fy,
+        intrinsics.cx,
+        intrinsics.cy
+    );
+
+    let mut images = format!(
+        "# Image list with two lines of data per image:\n\
+         #   IMAGE_ID, QW, QX, QY, QZ, TX, TY, TZ, CAMERA_ID, NAME\n\
+         #   POINTS2D[] as (X, Y, POINT3D_ID)\n\
+         # Number of images: {}\n",
+        manifest.frames.len()
+    );
+    for (index, frame) in manifest.frames.iter().enumerate() {
+        // COLMAP store world to camera in the OpenCV convention ( +Y down, camera looking toward +Z ), so flip the Y and Z
+        // axis of bevy's camera before inverting it
+        let camera_to_world = Mat4::from_cols_array_2d(&frame.camera_to_world).transpose()
+            * Mat4::from_scale(Vec3::new(1.0, -1.0, -1.0));
+        let (_, rotation, translation) = camera_to_world.inverse().to_scale_rotation_translation();
+        images.push_str(&format!(
+            "{} {} {} {} {} {} {} {} 1 {}\n\n",
+            index + 1,
+            rotation.w,
+            rotation.x,
+            rotation.y,
+            rotation.z,
+            translation.x,
+            translation.y,
+            translation.z,
+            frame.file_name
+        ));
+    }
+
+    let points = "# 3D point list with one line of data per point:\n\
+                  #   POINT3D_ID, X, Y, Z, R, G, B, ERROR, TRACK[] as (IMAGE_ID, POINT2D_IDX)\n\
+                  # Number of points: 0\n";
+
+    if let Err(err) = create_dir_all(sparse_dir_path) {
+        eprintln!("❌ Failed to create {:?}: {}", sparse_dir_path, err);
+        return;
+    }
+    for (file_name, content) in [
+        ("cameras.txt", cameras.as_str()),
+        ("images.txt", images.as_str()),
+        ("points3D.txt", points),
+    ] {
+        if let Err(err) = write(sparse_dir_path.join(file_name), content) {
+            eprintln!(
+                "❌ Failed to write {:?}: {}",
+                sparse_dir_path.join(file_name),
+                err
+            );
+        }
+    }
+}
+
+fn write_json_file<T: Serialize>(path: &Path, value: &T) {
+    match OpenOptions::new()
+        .write(true)
+        .create(true) // Create the file if it doesn't exist
+        .truncate(true) // Truncate the file to ensure it's empty before writing
+        .open(path)
+    {
+        Ok(file) => {
+            if let Err(err) = serde_json::to_writer_pretty(file, value) {
+                eprintln!("❌ Failed to write {:?}: {}", path, err);
+            }
+        }
+        Err(err) => {
+            eprintln!("❌ Failed to create {:?}: {}", path, err);
+        }
+    }
+}
+
+// a missing file isn't an error, only report the files that can't be parsed
+fn read_json_file<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    let file = File::open(path).ok()?;
+    match serde_json::from_reader(file) {
+        Ok(value) => {
+            return Some(value);
+        }
+        Err(err) => {
+            eprintln!("❌ Failed to read {:?}: {}", path, err);
+            return None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{FrameRecord, PoseRecord};
+
+    fn frame(file_name: &str) -> FrameRecord {
+        return FrameRecord {
+            file_name: file_name.to_string(),
+            yaw: 0.0,
+            pitch: 0.0,
+            radius: 0.0,
+            camera_to_world: Mat4::IDENTITY.to_cols_array_2d(),
+            intrinsics: CameraIntrinsics {
+                width: 4,
+                height: 4,
+                fov_y: 1.0,
+                aspect_ratio: 1.0,
+                near: 0.1,
+                far: 1000.0,
+                fx: 2.0,
+                fy: 2.0,
+                cx: 2.0,
+                cy: 2.0,
+            },
+            bbox: None,
+            pose: None,
+            depth: None,
+            normal: None,
+            mask_file_name: None,
+            rgba_file_name: None,
+            instance_file_name: None,
+            animation: None,
+            lighting: None,
+            skybox: None,
+        };
+    }
+
+    // a model directory with its snapshots on disk, the channel images next to them are never listed
+    fn model_dir(output_dir: &Path, name: &str, frame_count: usize) -> (PathBuf, CaptureManifest) {
+        let dir_path = output_dir.join(name);
+        create_dir_all(&dir_path).unwrap();
+        let mut manifest = CaptureManifest::default();
+        for index in 0..frame_count {
+            let file_name = format!("{}_{}.jpg", name, index);
+            write(dir_path.join(&file_name), b"").unwrap();
+            write(dir_path.join(format!("{}_{}_depth.png", name, index)), b"").unwrap();
+            manifest.frames.push(frame(&file_name));
+        }
+        return (dir_path, manifest);
+    }
+
+    #[test]
+    fn yolo_lists_split_the_snapshots_of_every_model() {
+        let output_dir =
+            std::env::temp_dir().join(format!("mvc_yolo_lists_{}", std::process::id()));
+        let (car_dir, car) = model_dir(&output_dir, "car", 10);
+        let (cup_dir, mut cup) = model_dir(&output_dir, "cup", 2);
+        // recorded but never saved
+        cup.frames.push(frame("cup_missing.jpg"));
+
+        let (train, val) = yolo_image_lists(&output_dir, &[(&car_dir, car), (&cup_dir, cup)]);
+
+        assert_eq!(
+            val,
+            vec!["./car/car_4.jpg", "./car/car_9.jpg", "./cup/cup_1.jpg"]
+        );
+        assert_eq!(train.len(), 9);
+        assert!(train.contains(&"./cup/cup_0.jpg".to_string()));
+        assert!(train
+            .iter()
+            .chain(val.iter())
+            .all(|path| path.ends_with(".jpg") && !path.contains("missing")));
+    }
+
+    #[test]
+    fn yolo_lists_keep_a_single_snapshot_for_training() {
+        let output_dir =
+            std::env::temp_dir().join(format!("mvc_yolo_single_{}", std::process::id()));
+        let (dir, manifest) = model_dir(&output_dir, "box", 1);
+
+        let (train, val) = yolo_image_lists(&output_dir, &[(&dir, manifest)]);
+
+        assert_eq!(train, vec!["./box/box_0.jpg"]);
+        assert!(val.is_empty());
+    }
+
+    #[test]
+    fn bop_scene_gt_only_hold_bop_keys() {
+        let mut manifest = CaptureManifest::default();
+        manifest.frames.push(frame("car_0.jpg"));
+        let mut posed = frame("car_1.jpg");
+        posed.pose = Some(PoseRecord {
+            model_to_world: Mat4::IDENTITY.to_cols_array_2d(),
+            model_to_camera_rotation: Mat3::IDENTITY.to_cols_array_2d(),
+            model_to_camera_translation: [0.0, 0.0, 2.0],
+            bbox_3d: [[0.0; 3]; 8],
+            projected_bbox_3d: [Some([1.0, 2.0]); 8],
+        });
+        manifest.frames.push(posed);
+
+        let scene = bop_scene(&manifest).unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&scene.scene_gt).unwrap(),
+            serde_json::json!({
+                "0": [],
+                "1": [{
+                    "cam_R_m2c": [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0],
+                    "cam_t_m2c": [0.0, 0.0, 2000.0],
+                    "obj_id": 1
+                }]
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(&scene.scene_images).unwrap(),
+            serde_json::json!({ "0": "car_0.jpg", "1": "car_1.jpg" })
+        );
+        assert!(scene.scene_projected_bbox_3d[&0].is_empty());
+        assert_eq!(
+            scene.scene_projected_bbox_3d[&1][0].projected_bbox_3d,
+            [Some([1.0, 2.0]); 8]
+        );
+        assert_eq!(scene.scene_camera.len(), 2);
+    }
+
+    #[test]
+    fn no_bop_scene_without_any_pose() {
+        let mut manifest = CaptureManifest::default();
+        manifest.frames.push(frame("car_0.jpg"));
+
+        assert!(bop_scene(&manifest).is_none());
+    }
+}
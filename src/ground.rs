@@ -0,0 +1,117 @@
+use bevy::{
+    pbr::NotShadowCaster,
+    prelude::*,
+    render::{
+        primitives::Aabb,
+        render_resource::{AsBindGroup, ShaderRef},
+        view::RenderLayers,
+    },
+};
+
+use crate::{
+    capture::model_bounds,
+    components::{CaptureChannelMesh, GroundPlane, ModelRotateReposition},
+    types::{GroundPlaneMode, GroundPlaneSettings},
+};
+
+// only the operation window camera see the ground plane, the RGBA channel keep a matte of the model alone
+pub const GROUND_RENDER_LAYER: usize = 5;
+
+// transparent except where the model cast a shadow from a directional light, see assets/shaders/ground_shadow.wgsl
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+pub struct GroundShadowMaterial {
+    #[uniform(0)]
+    pub shadow_opacity: f32,
+}
+
+impl Material for GroundShadowMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "embedded://mvc/assets/shaders/ground_shadow.wgsl".into()
+    }
+
+    fn alpha_mode(&self) -> AlphaMode {
+        AlphaMode::Blend
+    }
+}
+
+// spawn the ground plane at the height of the bottom of the model, the plane never cast a shadow itself
+pub fn spawn_ground_plane(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    standard_materials: &mut Assets<StandardMaterial>,
+    shadow_materials: &mut Assets<GroundShadowMaterial>,
+    settings: &GroundPlaneSettings,
+    ground_height: f32,
+    size: f32,
+) -> Entity {
+    let mesh = Mesh3d(meshes.add(Plane3d::default().mesh().size(size, size)));
+    let transform = Transform::from_xyz(0.0, ground_height, 0.0);
+
+    return match settings.mode {
+        GroundPlaneMode::Visible => commands
+            .spawn((
+                mesh,
+                MeshMaterial3d(standard_materials.add(StandardMaterial {
+                    base_color: Color::srgb(
+                        settings.color[0],
+                        settings.color[1],
+                        settings.color[2],
+                    ),
+                    perceptual_roughness: 1.0,
+                    ..default()
+                })),
+                transform,
+                NotShadowCaster,
+                RenderLayers::layer(GROUND_RENDER_LAYER),
+                GroundPlane,
+            ))
+            .id(),
+        GroundPlaneMode::ShadowOnly => commands
+            .spawn((
+                mesh,
+                MeshMaterial3d(shadow_materials.add(GroundShadowMaterial {
+                    shadow_opacity: settings.shadow_opacity.clamp(0.0, 1.0),
+                })),
+                transform,
+                NotShadowCaster,
+                RenderLayers::layer(GROUND_RENDER_LAYER),
+                GroundPlane,
+            ))
+            .id(),
+    };
+}
+
+// keep the ground plane under the model when it is moved, rotated or animated, the plane follow the lowest corner of the
+// model's bounding box and stay centered under the model, the transforms are the ones of the previous frame
+pub fn follow_model_with_ground_plane(
+    models: Query<&GlobalTransform, With<ModelRotateReposition>>,
+    meshes: Query<
+        (&GlobalTransform, &Aabb),
+        (
+            With<Mesh3d>,
+            Without<CaptureChannelMesh>,
+            Without<GroundPlane>,
+        ),
+    >,
+    mut ground_planes: Query<&mut Transform, With<GroundPlane>>,
+) {
+    let model_transform = match models.get_single() {
+        Ok(model_transform) => model_transform,
+        Err(_) => return,
+    };
+    let model_bottom = model_bounds(Some(model_transform), meshes.iter())
+        .world_corners
+        .iter()
+        .fold(f32::MAX, |bottom, corner| bottom.min(corner.y));
+    if model_bottom == f32::MAX {
+        return;
+    }
+
+    let model_translation = model_transform.translation();
+    let translation = Vec3::new(model_translation.x, model_bottom, model_translation.z);
+    for mut transform in ground_planes.iter_mut() {
+        if transform.translation != translation {
+            transform.translation = translation;
+        }
+    }
+}
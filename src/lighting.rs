@@ -0,0 +1,272 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use bevy::prelude::*;
+
+use crate::{
+    components::RandomizedLight,
+    resource::DefaultLighting,
+    sampler::{frame_rng, SplitMix64},
+    types::{LightRecord, LightingRandomization, LightingRecord, LightingRig, RigLightKind},
+};
+
+// the random stream of the lighting, the viewpoints are sampled from the seed itself
+const LIGHTING_STREAM: u64 = 0x4C49_4748_5449_4E47;
+
+// just short of the zenith
+const MAX_LIGHT_ELEVATION: f32 = FRAC_PI_2 - 0.01;
+
+// the lights of the scene that are not randomized are hidden while the lighting is randomized, whatever their kind
+pub type SceneLightFilter = Or<(With<DirectionalLight>, With<PointLight>, With<SpotLight>)>;
+
+// spawn the lights of the rig and set the ambient light, the entities are despawned with the operation window, the
+// brightest directional light ( the key light ) cast shadows when shadows_enabled is set ( the ground plane only catch
+// directional shadows ) so that there is a single extra shadow map to render, the other lights keep the setting of the rig
+pub fn spawn_lighting_rig(
+    commands: &mut Commands,
+    rig: &LightingRig,
+    shadows_enabled: bool,
+) -> Vec<Entity> {
+    let key_light = key_light_index(rig.lights.iter().map(|rig_light| match rig_light.kind {
+        RigLightKind::Directional { illuminance } => Some(illuminance),
+        _ => None,
+    }));
+    let mut light_entities = vec![];
+    for (index, rig_light) in rig.lights.iter().enumerate() {
+        let color = Color::srgb(rig_light.color[0], rig_light.color[1], rig_light.color[2]);
+        let transform = Transform::from_translation(Vec3::from_array(rig_light.translation))
+            .with_rotation(Quat::from_euler(
+                EulerRot::XYZ,
+                rig_light.rotation[0],
+                rig_light.rotation[1],
+                rig_light.rotation[2],
+            ));
+        let light_entity = match rig_light.kind {
+            RigLightKind::Directional { illuminance } => commands
+                .spawn((
+                    DirectionalLight {
+                        illuminance,
+                        color,
+                        shadows_enabled: rig_light.shadows_enabled
+                            || (shadows_enabled && key_light == Some(index)),
+                        ..default()
+                    },
+                    transform,
+                ))
+                .id(),
+            RigLightKind::Point { intensity, range } => commands
+                .spawn((
+                    PointLight {
+                        intensity,
+                        range,
+                        color,
+                        shadows_enabled: rig_light.shadows_enabled,
+                        ..default()
+                    },
+                    transform,
+                ))
+                .id(),
+            RigLightKind::Spot {
+                intensity,
+                range,
+                inner_angle,
+                outer_angle,
+            } => commands
+                .spawn((
+                    SpotLight {
+                        intensity,
+                        range,
+                        inner_angle,
+                        outer_angle,
+                        color,
+                        shadows_enabled: rig_light.shadows_enabled,
+                        ..default()
+                    },
+                    transform,
+                ))
+                .id(),
+        };
+        light_entities.push(light_entity);
+    }
+
+    // ambient light to fill in any unlit areas
+    commands.insert_resource(AmbientLight {
+        color: Color::WHITE,
+        brightness: rig.ambient_brightness,
+    });
+
+    return light_entities;
+}
+
+// replace the lights of the scene by random ones for a live capture frame, the same seed and frame always give the same
+// lighting, the brightest random light cast shadows when shadows_enabled is set ( for the ground plane )
+pub fn randomize_lighting(
+    commands: &mut Commands,
+    randomization: &LightingRandomization,
+    seed: u64,
+    frame_index: usize,
+    shadows_enabled: bool,
+    ambient_light: &mut AmbientLight,
+    default_lighting: &mut DefaultLighting,
+    lights: &mut Query<(Entity, &mut Visibility, Has<RandomizedLight>), SceneLightFilter>,
+) -> LightingRecord {
+    // the lights of the previous frame are removed and the fixed ones are hidden until the live capture ends
+    for (light_entity, mut visibility, randomized) in lights.iter_mut() {
+        if randomized {
+            commands.entity(light_entity).despawn();
+        } else {
+            *visibility = Visibility::Hidden;
+        }
+    }
+    if default_lighting.ambient_brightness.is_none() {
+        default_lighting.ambient_brightness = Some(ambient_light.brightness);
+    }
+
+    let mut rng = frame_rng(seed, LIGHTING_STREAM, frame_index);
+    let light_count_range = (
+        randomization.light_count.0,
+        randomization.light_count.1.max(randomization.light_count.0),
+    );
+    let light_count = light_count_range.0
+        + (rng.next_u64() % (light_count_range.1 - light_count_range.0 + 1) as u64) as usize;
+
+    let mut light_records = vec![];
+    for _ in 0..light_count {
+        let yaw = random_in_range(&mut rng, (-PI, PI));
+        // a light from straight above or below is degenerate with the +Y up vector of its transform
+        let elevation = random_in_range(&mut rng, randomization.elevation)
+            .clamp(-MAX_LIGHT_ELEVATION, MAX_LIGHT_ELEVATION);
+        let illuminance = random_in_range(&mut rng, randomization.illuminance);
+        let color_temperature = random_in_range(&mut rng, randomization.color_temperature);
+
+        // the light come from a point above the horizon toward the model at the origin
+        let direction = -Vec3::new(
+            elevation.cos() * yaw.sin(),
+            elevation.sin(),
+            elevation.cos() * yaw.cos(),
+        );
+        let color = color_temperature_to_rgb(color_temperature);
+        light_records.push(LightRecord {
+            direction: direction.to_array(),
+            illuminance,
+            color_temperature,
+            color: color.to_linear().to_f32_array_no_alpha(),
+        });
+    }
+
+    let key_light = key_light_index(
+        light_records
+            .iter()
+            .map(|light_record| Some(light_record.illuminance)),
+    );
+    for (index, light_record) in light_records.iter().enumerate() {
+        commands.spawn((
+            DirectionalLight {
+                illuminance: light_record.illuminance,
+                color: Color::linear_rgb(
+                    light_record.color[0],
+                    light_record.color[1],
+                    light_record.color[2],
+                ),
+                shadows_enabled: shadows_enabled && key_light == Some(index),
+                ..default()
+            },
+            Transform::IDENTITY.looking_to(Vec3::from_array(light_record.direction), Vec3::Y),
+            RandomizedLight,
+        ));
+    }
+    ambient_light.brightness = random_in_range(&mut rng, randomization.ambient_brightness);
+
+    return LightingRecord {
+        ambient_brightness: ambient_light.brightness,
+        lights: light_records,
+    };
+}
+
+// put back the lights of the scene once the live capture ends
+pub fn restore_default_lighting(
+    mut commands: Commands,
+    mut ambient_light: ResMut<AmbientLight>,
+    mut default_lighting: ResMut<DefaultLighting>,
+    mut lights: Query<(Entity, &mut Visibility, Has<RandomizedLight>), SceneLightFilter>,
+) {
+    let ambient_brightness = match default_lighting.ambient_brightness.take() {
+        Some(ambient_brightness) => ambient_brightness,
+        None => return,
+    };
+
+    ambient_light.brightness = ambient_brightness;
+    for (light_entity, mut visibility, randomized) in lights.iter_mut() {
+        if randomized {
+            commands.entity(light_entity).despawn();
+        } else {
+            *visibility = Visibility::Inherited;
+        }
+    }
+}
+
+// the index of the brightest directional light, the other lights are None
+fn key_light_index(illuminances: impl Iterator<Item = Option<f32>>) -> Option<usize> {
+    return illuminances
+        .enumerate()
+        .filter_map(|(index, illuminance)| illuminance.map(|illuminance| (index, illuminance)))
+        .fold(
+            None,
+            |key_light: Option<(usize, f32)>, (index, illuminance)| match key_light {
+                Some((_, key_illuminance)) if key_illuminance >= illuminance => key_light,
+                _ => Some((index, illuminance)),
+            },
+        )
+        .map(|(index, _)| index);
+}
+
+fn random_in_range(rng: &mut SplitMix64, range: (f32, f32)) -> f32 {
+    return range.0 + (range.1 - range.0) * rng.next_f32();
+}
+
+// approximation of the color of a black body at this temperature ( kelvin ), good enough between 1000K and 40000K
+fn color_temperature_to_rgb(kelvin: f32) -> Color {
+    let temperature = kelvin.clamp(1000.0, 40000.0) / 100.0;
+
+    let red = if temperature <= 66.0 {
+        255.0
+    } else {
+        329.699 * (temperature - 60.0).powf(-0.133_205)
+    };
+    let green = if temperature <= 66.0 {
+        99.470_8 * temperature.ln() - 161.119_57
+    } else {
+        288.122 * (temperature - 60.0).powf(-0.075_514_85)
+    };
+    let blue = if temperature >= 66.0 {
+        255.0
+    } else if temperature <= 19.0 {
+        0.0
+    } else {
+        138.517_73 * (temperature - 10.0).ln() - 305.044_8
+    };
+
+    return Color::srgb(
+        red.clamp(0.0, 255.0) / 255.0,
+        green.clamp(0.0, 255.0) / 255.0,
+        blue.clamp(0.0, 255.0) / 255.0,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_light_is_the_brightest_directional_light() {
+        assert_eq!(
+            key_light_index([Some(300.0), None, Some(900.0), Some(500.0)].into_iter()),
+            Some(2)
+        );
+        // the first one of the default rig, the light from the top
+        assert_eq!(
+            key_light_index(LightingRig::default().lights.iter().map(|_| Some(800.0))),
+            Some(0)
+        );
+        assert_eq!(key_light_index([None, None].into_iter()), None);
+    }
+}
@@ -1,3 +1,5 @@
+use std::{collections::HashMap, path::Path};
+
 use bevy::asset::embedded_asset;
 use bevy::pbr::DirectionalLightShadowMap;
 use bevy::prelude::*;
@@ -21,6 +23,7 @@ mod utils;
 use utils::*;
 
 mod capture;
+use capture::*;
 
 mod camera;
 use camera::*;
@@ -28,6 +31,29 @@ use camera::*;
 mod types;
 use types::*;
 
+mod batch;
+use batch::*;
+
+mod export;
+use export::*;
+
+mod channels;
+use channels::*;
+
+mod sampler;
+
+mod model_loader;
+use model_loader::*;
+
+mod animation;
+use animation::*;
+
+mod lighting;
+use lighting::*;
+
+mod ground;
+use ground::*;
+
 // Note:
 // The keyboard command when in the interactive mode
 //
@@ -37,30 +63,90 @@ use types::*;
 // Q: render the previous model
 // E: render the next model
 // space: enter or exit live capture mode
+// R: resume the interrupted live capture of the current model from its saved progress
 // arrow key: rotate model
 // wasd: move the model
 // mouse wheel: zoom in or out
 // mouse drag: rotate the model
+// P: play or pause the animation of the model
+// [ / ]: step the animation one frame backward / forward
+// N: switch to the next animation clip
+//
+// Headless batch capture
+//
+// mvc --skybox <skybox file> --output <output directory> <model file> [<model file> ...]
+// mvc --job <capture job file> [--skybox <skybox file>] [--output <output directory>] [<model file> ...]
+// will live capture every model one after another without any user interaction and exit once done,
+// the exit status will be non-zero if any of the file failed to load or the operation window was closed
 
-fn main() {
+fn main() -> AppExit {
     // init the app setting
     let app_settings: AppSettings = init_app();
 
+    // check if MVC was started for a batch capture
+    let batch_args = match parse_batch_args(std::env::args().skip(1).collect()) {
+        Ok(batch_args) => batch_args,
+        Err(err) => {
+            eprintln!("{}", err);
+            return AppExit::error();
+        }
+    };
+
     let mut app: App = App::new();
     // set the resource
     app.insert_resource(DirectionalLightShadowMap { size: 4096 });
-    app.insert_resource(AssetPath {
-        models_path: vec![],
-        skybox_path: "".to_string(),
-        current_model_path_count: 0,
-    });
-    app.insert_resource(SavePathList {
-        base_dir_path: app_settings.image_save_dir.clone(),
-        save_path_list: vec![],
-        current_path_count: 0,
-    });
+    match batch_args {
+        Some(batch_args) => {
+            let mut asset_path = AssetPath {
+                models_path: vec![],
+                skybox_path: batch_args.skybox_path,
+                current_model_path_count: 0,
+                model_overrides: HashMap::new(),
+                gltf_scene: app_settings.gltf_scene.clone(),
+            };
+            let mut save_settings = SavePathList {
+                base_dir_path: batch_args.output_dir.clone(),
+                save_path_list: vec![],
+                current_path_count: 0,
+            };
+            for model_path in batch_args.models_path.iter() {
+                if let Err(err) = add_model_entries(
+                    &mut asset_path,
+                    &mut save_settings,
+                    &batch_args.output_dir,
+                    Path::new(""),
+                    Path::new(model_path),
+                    batch_args.model_overrides.get(model_path).cloned(),
+                ) {
+                    eprintln!("❌ {}", err);
+                    return AppExit::error();
+                }
+            }
+            app.insert_resource(asset_path);
+            app.insert_resource(save_settings);
+            app.insert_resource(BatchCapture {
+                stage: BatchCaptureStage::OpenOperationWindow,
+                exit_on_finish: true,
+            });
+        }
+        None => {
+            app.insert_resource(AssetPath {
+                models_path: vec![],
+                skybox_path: "".to_string(),
+                current_model_path_count: 0,
+                model_overrides: HashMap::new(),
+                gltf_scene: app_settings.gltf_scene.clone(),
+            });
+            app.insert_resource(SavePathList {
+                base_dir_path: app_settings.image_save_dir.clone(),
+                save_path_list: vec![],
+                current_path_count: 0,
+            });
+        }
+    }
     app.insert_resource(SkyboxAttribute {
         skybox_handler: None,
+        skybox_path: "".to_string(),
     });
     app.insert_resource(OperationWindowRelatedEntities {
         window: None,
@@ -84,12 +170,52 @@ fn main() {
         model_reposition_sensitivity: app_settings.model_reposition_sensitivity,
         mouse_sensitivity: app_settings.mouse_sensitivity,
         zoom_sensitivity: app_settings.zoom_sensitivity,
+        ground_plane: app_settings.ground_plane.clone(),
     });
 
     app.insert_resource(LiveCaptureOperationSettings {
+        configured_live_capture_iteration: app_settings.live_capture_iteration,
         live_capture_iteration: app_settings.live_capture_iteration,
         live_capture_iteration_current_counter: 0,
         live_capture_coordinate_list: vec![(0., 0., 0.)],
+        viewpoint_sampler: app_settings.viewpoint_sampler.clone(),
+        seed: app_settings.seed,
+        live_capture_seed: app_settings.seed,
+        skip_existing: false,
+        max_snapshots_in_flight: app_settings.max_snapshots_in_flight.max(1),
+        animation_capture: app_settings.animation_capture.clone(),
+        lighting_randomization: app_settings.lighting_randomization.clone(),
+        live_capture_animation_times: vec![],
+    });
+    app.insert_resource(ModelAnimation::default());
+    app.insert_resource(LightingRigSettings {
+        rig: settings_lighting_rig(&app_settings),
+    });
+    app.insert_resource(DefaultLighting::default());
+    app.insert_resource(settings_skybox_pool(&app_settings));
+    app.insert_resource(SnapshotsInFlight::default());
+    app.insert_resource(CaptureManifestRecord {
+        dir_path: "".to_string(),
+        manifest: CaptureManifest::default(),
+        progress: None,
+        pending_write: false,
+    });
+    app.insert_resource(CaptureChannels {
+        depth_output: app_settings.depth_output,
+        depth_material: None,
+        depth_image: None,
+        normal_output: app_settings.normal_output,
+        normal_material: None,
+        normal_image: None,
+        mask_output: app_settings.mask_output,
+        mask_material: None,
+        mask_image: None,
+        rgba_output: app_settings.rgba_output,
+        rgba_image: None,
+        instance_output: app_settings.instance_output,
+        instance_image: None,
+        instance_scene_entity: None,
+        instance_legend: vec![],
     });
     // set the plugins
     app.add_plugins((DefaultPlugins.set(WindowPlugin {
@@ -116,11 +242,29 @@ fn main() {
         ..default()
     }),));
     app.add_plugins(EmbeddedAssetPlugin);
+    app.add_plugins(MaterialPlugin::<CaptureDepthMaterial>::default());
+    app.add_plugins(MaterialPlugin::<CaptureNormalMaterial>::default());
+    app.add_plugins(MaterialPlugin::<CaptureMaskMaterial>::default());
+    app.add_plugins(MaterialPlugin::<CaptureInstanceMaterial>::default());
+    app.add_plugins(MaterialPlugin::<GroundShadowMaterial>::default());
+    app.init_asset_loader::<ObjLoader>();
+    app.init_asset_loader::<StlLoader>();
+    app.init_asset_loader::<PlyLoader>();
+    app.add_observer(screenshot_captured);
+    app.add_systems(Update, expire_snapshots_in_flight);
     // set initial state
     app.insert_state(AppState::MainMenu);
     app.insert_state(OperationState::None);
     app.insert_state(CameraFovInitializedState::NotInitialized);
-    app.add_systems(Startup, menu);
+    app.add_systems(Startup, (menu, init_capture_channels));
+    app.add_systems(
+        OnExit(OperationState::LiveCapture),
+        restore_default_lighting,
+    );
+    app.add_systems(
+        Startup,
+        batch_capture_init.run_if(resource_exists::<BatchCapture>),
+    );
     app.add_systems(
         Update,
         (
@@ -157,10 +301,59 @@ fn main() {
                     .and(in_state(CameraFovInitializedState::Initialized)),
             ),
             switch_state_on_window_event,
+            write_capture_manifest.run_if(not(in_state(OperationState::LiveCapture))),
+            spawn_capture_channel_cameras,
+            attach_capture_channel_meshes.run_if(in_state(AppState::OperationMode)),
+            resize_capture_channel_targets.run_if(in_state(AppState::OperationMode)),
+            apply_model_skybox.run_if(in_state(AppState::OperationMode)),
+            setup_model_animation.run_if(in_state(AppState::OperationMode)),
+            animation_scrubber.run_if(
+                in_state(AppState::OperationMode)
+                    .and(in_state(OperationState::Interactive))
+                    .and(in_state(CameraFovInitializedState::Initialized)),
+            ),
+            pose_model_animation
+                .after(live_capture_camera)
+                .after(animation_scrubber)
+                .run_if(in_state(AppState::OperationMode)),
+        ),
+    );
+    app.add_systems(
+        Update,
+        follow_model_with_ground_plane.run_if(
+            in_state(AppState::OperationMode).and(in_state(CameraFovInitializedState::Initialized)),
         ),
     );
+    // the morph weights are passed down from the animated node and the joints are placed in PostUpdate
+    app.add_systems(
+        PostUpdate,
+        (
+            sync_capture_channel_morph_weights.after(bevy::render::mesh::inherit_weights),
+            update_skinned_mesh_bounds
+                .after(TransformSystem::TransformPropagate)
+                .before(bevy::render::view::VisibilitySystems::CheckVisibility),
+        )
+            .run_if(in_state(AppState::OperationMode)),
+    );
+    app.add_systems(
+        Update,
+        (
+            batch_open_operation_window.run_if(in_state(AppState::MainMenu)),
+            batch_check_model_load.run_if(
+                in_state(AppState::OperationMode)
+                    .and(in_state(CameraFovInitializedState::NotInitialized)),
+            ),
+            batch_capture_progress.run_if(
+                in_state(AppState::OperationMode)
+                    .and(in_state(OperationState::Interactive))
+                    .and(in_state(CameraFovInitializedState::Initialized)),
+            ),
+            display_batch_capture_progress.after(live_capture_camera),
+        )
+            .run_if(resource_exists::<BatchCapture>),
+    );
 
-    app.run();
+    return app.run();
 }
 
 struct EmbeddedAssetPlugin;
@@ -173,5 +366,10 @@ impl Plugin for EmbeddedAssetPlugin {
         embedded_asset!(app, omit_prefix, "assets/fonts/FiraSans-Bold.ttf");
         embedded_asset!(app, omit_prefix, "assets/pisa_diffuse_rgb9e5_zstd.ktx2");
         embedded_asset!(app, omit_prefix, "assets/pisa_specular_rgb9e5_zstd.ktx2");
+        embedded_asset!(app, omit_prefix, "assets/shaders/capture_depth.wgsl");
+        embedded_asset!(app, omit_prefix, "assets/shaders/capture_normal.wgsl");
+        embedded_asset!(app, omit_prefix, "assets/shaders/capture_mask.wgsl");
+        embedded_asset!(app, omit_prefix, "assets/shaders/capture_instance.wgsl");
+        embedded_asset!(app, omit_prefix, "assets/shaders/ground_shadow.wgsl");
     }
 }
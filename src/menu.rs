@@ -1,23 +1,28 @@
 use std::{
     fs::{File, OpenOptions},
-    path::PathBuf,
+    path::Path,
 };
 
 use bevy::prelude::*;
 
 use crate::{
     components::{
-        ClearModelAssetsButton, ClearSkyboxAssetsButton, InteractiveMode, ModelPathLabel,
-        SkyboxPathLabel,
+        CaptureAllButton, CaptureProgressLabel, ClearModelAssetsButton, ClearSkyboxAssetsButton,
+        InteractiveMode, ModelPathLabel, SkyboxPathLabel,
     },
     render::interactive,
     resource::{
-        AssetPath, LiveCaptureOperationSettings, OperationSettings, OperationWindowRelatedEntities,
-        SavePathList, SkyboxAttribute,
+        AssetPath, BatchCapture, CaptureChannels, LightingRigSettings,
+        LiveCaptureOperationSettings, OperationSettings, OperationWindowRelatedEntities,
+        SavePathList, SkyboxAttribute, SkyboxPool,
+    },
+    states::{AppState, BatchCaptureStage, OperationState},
+    types::AppSettings,
+    utils::{
+        add_model_entries, check_json_file, check_model_file, check_skybox_file, find_model_files,
+        get_user_directory, is_capture_job_file, is_lighting_rig_file, load_capture_job,
+        load_lighting_rig, settings_lighting_rig, settings_skybox_pool,
     },
-    states::{AppState, OperationState},
-    types::{AppSettings, SavePath},
-    utils::{check_json_file, check_model_file, check_skybox_file, get_user_directory},
 };
 
 const MENU_FONT_SIZE: f32 = 50.;
@@ -106,6 +111,26 @@ pub fn menu(mut commands: Commands, asset_server: Res<AssetServer>) {
                         SkyboxPathLabel,
                     ));
 
+                    // to show the progress of the capture all operation
+                    parent.spawn((
+                        Text::new("[ capture all ]: -"),
+                        Node {
+                            margin: UiRect {
+                                top: Val::Px(5.0),
+                                ..Default::default()
+                            },
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        TextFont {
+                            font: font.clone(),
+                            font_size: PATH_FONT_SIZE,
+                            ..default()
+                        },
+                        CaptureProgressLabel,
+                    ));
+
                     // Button to Start 3d render operation
                     parent
                         .spawn((
@@ -137,6 +162,37 @@ pub fn menu(mut commands: Commands, asset_server: Res<AssetServer>) {
                             TextColor(Color::srgb(0.9, 0.9, 0.9)),
                         ));
 
+                    // Button to live capture every model one after another
+                    parent
+                        .spawn((
+                            Button,
+                            Node {
+                                margin: UiRect {
+                                    top: Val::Px(10.0),
+                                    ..Default::default()
+                                },
+                                height: Val::Px(50.0),
+                                padding: UiRect::all(Val::Px(10.0)),
+                                border: UiRect::all(Val::Px(2.0)),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            BorderColor(Color::BLACK),
+                            BorderRadius::MAX,
+                            BackgroundColor(Color::srgb(0.15, 0.15, 0.15)),
+                            CaptureAllButton,
+                        ))
+                        .with_child((
+                            Text::new("Capture All Models"),
+                            TextFont {
+                                font: font.clone(),
+                                font_size: FONT_SIZE,
+                                ..default()
+                            },
+                            TextColor(Color::srgb(0.9, 0.9, 0.9)),
+                        ));
+
                     // Button to clear all current models assets path
                     parent
                         .spawn((
@@ -266,6 +322,14 @@ pub fn menu(mut commands: Commands, asset_server: Res<AssetServer>) {
                             ..default()
                         },
                     ));
+                    parent.spawn((
+                        Text::new("R : resume the interrupted live capture"),
+                        TextFont {
+                            font: font.clone(),
+                            font_size: PATH_FONT_SIZE,
+                            ..default()
+                        },
+                    ));
                     parent.spawn((
                         Text::new("ARROW KEY : rotate model"),
                         TextFont {
@@ -298,16 +362,41 @@ pub fn menu(mut commands: Commands, asset_server: Res<AssetServer>) {
                             ..default()
                         },
                     ));
+                    parent.spawn((
+                        Text::new("P : play or pause the animation of the model"),
+                        TextFont {
+                            font: font.clone(),
+                            font_size: PATH_FONT_SIZE,
+                            ..default()
+                        },
+                    ));
+                    parent.spawn((
+                        Text::new("[ / ] : step the animation one frame backward / forward"),
+                        TextFont {
+                            font: font.clone(),
+                            font_size: PATH_FONT_SIZE,
+                            ..default()
+                        },
+                    ));
+                    parent.spawn((
+                        Text::new("N : switch to the next animation clip"),
+                        TextFont {
+                            font: font.clone(),
+                            font_size: PATH_FONT_SIZE,
+                            ..default()
+                        },
+                    ));
                 });
         });
 }
 
 // button click system to handle the entering of operation mode
 pub fn button_click_system(
-    commands: Commands,
+    mut commands: Commands,
     asset_server: Res<AssetServer>,
     mut asset_path: ResMut<AssetPath>,
     interactive_mode: Query<&Interaction, (Changed<Interaction>, With<InteractiveMode>)>,
+    capture_all: Query<&Interaction, (Changed<Interaction>, With<CaptureAllButton>)>,
     clear_model_assets: Query<&Interaction, (Changed<Interaction>, With<ClearModelAssetsButton>)>,
     clear_skybox_assets: Query<&Interaction, (Changed<Interaction>, With<ClearSkyboxAssetsButton>)>,
     operation_window: ResMut<OperationWindowRelatedEntities>,
@@ -325,11 +414,12 @@ pub fn button_click_system(
         Query<(&mut TextColor, &ModelPathLabel)>,
         Query<(&mut TextColor, &SkyboxPathLabel)>,
     )>,
-    operation_settings: Res<OperationSettings>,
+    (operation_settings, lighting_rig): (Res<OperationSettings>, Res<LightingRigSettings>),
     mut save_settings: ResMut<SavePathList>,
 ) {
     // Check if the files and all were valid then enter window to render 3d model or warn user about invalid file
-    if let Ok(Interaction::Pressed) = interactive_mode.get_single() {
+    let capture_all_pressed = matches!(capture_all.get_single(), Ok(Interaction::Pressed));
+    if matches!(interactive_mode.get_single(), Ok(Interaction::Pressed)) || capture_all_pressed {
         let mut proceed = true;
 
         if asset_path.models_path.len() <= 0 {
@@ -354,6 +444,18 @@ pub fn button_click_system(
             proceed = false;
         }
 
+        if proceed && capture_all_pressed {
+            // the batch capture systems take over from here, starting with the first model
+            println!("Capture all models 🎥");
+            asset_path.current_model_path_count = 0;
+            save_settings.current_path_count = 0;
+            commands.insert_resource(BatchCapture {
+                stage: BatchCaptureStage::OpenOperationWindow,
+                exit_on_finish: false,
+            });
+            return;
+        }
+
         println!("Enter Opration Mode 👷‍♂️");
         if proceed {
             if asset_server.is_loaded(skybox_attributes.skybox_handler.as_ref().unwrap()) {
@@ -365,6 +467,7 @@ pub fn button_click_system(
                     skybox_attributes,
                     operation_window,
                     operation_settings,
+                    lighting_rig,
                 );
                 app_state.set(AppState::OperationMode);
                 operation_state.set(OperationState::Interactive)
@@ -381,6 +484,7 @@ pub fn button_click_system(
         // remove all model path and reset the current model path count
         asset_path.models_path = vec![];
         asset_path.current_model_path_count = 0;
+        asset_path.model_overrides.clear();
 
         for (mut text, _) in path_label_param_set.p0().iter_mut() {
             text.0 = "[ 3d model asset(s) ]: -".to_string();
@@ -415,6 +519,9 @@ pub fn file_drag_and_drop_system(
     mut save_settings: ResMut<SavePathList>,
     mut operation_settings: ResMut<OperationSettings>,
     mut live_capture_settings: ResMut<LiveCaptureOperationSettings>,
+    mut capture_channels: ResMut<CaptureChannels>,
+    mut lighting_rig: ResMut<LightingRigSettings>,
+    mut skybox_pool: ResMut<SkyboxPool>,
 ) {
     for event in events.read() {
         if let FileDragAndDrop::DroppedFile { window, path_buf } = event {
@@ -424,28 +531,63 @@ pub fn file_drag_and_drop_system(
             );
             let p = path_buf.to_str().unwrap().to_string();
 
-            if check_model_file(&p) {
-                let string_path = path_buf.to_str().unwrap().to_string();
-
-                // check if we already have the file path info within the models_path array to prevent duplicate model rendering
-                if !three_d_model_asset_path.models_path.contains(&string_path) {
-                    // to check if model file then save the path, the directory name and file prefix
-                    three_d_model_asset_path.models_path.push(string_path);
-                    let filename = path_buf.file_stem().unwrap().to_str().unwrap().to_string();
-                    let current_dir_path = PathBuf::from(&save_settings.base_dir_path)
-                        .join(&filename)
-                        .to_str()
-                        .unwrap()
-                        .to_string();
-                    let file_name_prefix = filename;
-
-                    let image_save_path = SavePath {
-                        current_dir_path,
-                        file_name_prefix,
-                    };
+            if path_buf.is_dir() {
+                // import every model inside the folder, the snapshots are saved in the same sub folders ( starting with
+                // the dropped folder itself )
+                let root_dir_path = path_buf.parent().unwrap_or(Path::new(""));
+                let mut new_model_count = 0;
+                for model_file in find_model_files(path_buf) {
+                    let sub_dir_path = model_file
+                        .parent()
+                        .and_then(|dir_path| dir_path.strip_prefix(root_dir_path).ok())
+                        .unwrap_or(Path::new(""));
+                    let base_dir_path = save_settings.base_dir_path.clone();
+                    match add_model_entries(
+                        &mut three_d_model_asset_path,
+                        &mut save_settings,
+                        &base_dir_path,
+                        sub_dir_path,
+                        &model_file,
+                        None,
+                    ) {
+                        Ok(new_entry_count) => new_model_count += new_entry_count,
+                        Err(err) => eprintln!("❌ {}", err),
+                    }
+                }
+                println!(
+                    "📂 Found {} new model(s) in {:?}",
+                    new_model_count, path_buf
+                );
 
-                    save_settings.save_path_list.push(image_save_path);
+                for (mut text, _) in &mut path_label_param_set.p0().iter_mut() {
+                    text.0 = format!(
+                        "[ 3d model asset(s) ]: {} files",
+                        three_d_model_asset_path.models_path.len()
+                    )
+                }
+                for (mut text, _) in &mut path_label_color_param_set.p0().iter_mut() {
+                    text.0 = Color::srgb(255., 255., 255.);
+                }
+            } else if check_model_file(&p) {
+                // to check if model file then save the path, the directory name and file prefix ( the entries already
+                // there are skipped to prevent duplicate model rendering )
+                let base_dir_path = save_settings.base_dir_path.clone();
+                let new_entry_count = match add_model_entries(
+                    &mut three_d_model_asset_path,
+                    &mut save_settings,
+                    &base_dir_path,
+                    Path::new(""),
+                    path_buf,
+                    None,
+                ) {
+                    Ok(new_entry_count) => new_entry_count,
+                    Err(err) => {
+                        eprintln!("❌ {}", err);
+                        continue;
+                    }
+                };
 
+                if new_entry_count > 0 {
                     for (mut text, _) in &mut path_label_param_set.p0().iter_mut() {
                         text.0 = format!(
                             "[ 3d model asset(s) ]: {} files",
@@ -465,6 +607,7 @@ pub fn file_drag_and_drop_system(
 
                 commands.insert_resource(SkyboxAttribute {
                     skybox_handler: Some(skybox_handle),
+                    skybox_path: three_d_model_asset_path.skybox_path.clone(),
                 });
                 for (mut text, _) in &mut path_label_param_set.p1().iter_mut() {
                     text.0 = format!("[ skybox asset ]: {}", p.clone())
@@ -472,6 +615,67 @@ pub fn file_drag_and_drop_system(
                 for (mut text, _) in &mut path_label_color_param_set.p1().iter_mut() {
                     text.0 = Color::srgb(255., 255., 255.);
                 }
+            } else if check_json_file(&p) && is_capture_job_file(path_buf) {
+                // a capture job add its models ( and skybox ) the same way as dropping them, it doesn't touch the settings
+                let job = match load_capture_job(path_buf) {
+                    Ok(job) => job,
+                    Err(err) => {
+                        eprintln!("❌ {}", err);
+                        continue;
+                    }
+                };
+                let output_dir = job
+                    .output_dir
+                    .unwrap_or(save_settings.base_dir_path.clone());
+                for job_model in job.models {
+                    if let Err(err) = add_model_entries(
+                        &mut three_d_model_asset_path,
+                        &mut save_settings,
+                        &output_dir,
+                        Path::new(""),
+                        Path::new(&job_model.path),
+                        Some(job_model.overrides),
+                    ) {
+                        eprintln!("❌ {}", err);
+                    }
+                }
+                println!(
+                    "📋 Capture job loaded, {} model(s) in total",
+                    three_d_model_asset_path.models_path.len()
+                );
+
+                for (mut text, _) in &mut path_label_param_set.p0().iter_mut() {
+                    text.0 = format!(
+                        "[ 3d model asset(s) ]: {} files",
+                        three_d_model_asset_path.models_path.len()
+                    )
+                }
+                for (mut text, _) in &mut path_label_color_param_set.p0().iter_mut() {
+                    text.0 = Color::srgb(255., 255., 255.);
+                }
+
+                if let Some(skybox_path) = job.skybox_path {
+                    three_d_model_asset_path.skybox_path = skybox_path.clone();
+                    commands.insert_resource(SkyboxAttribute {
+                        skybox_handler: Some(asset_server.load(skybox_path.clone())),
+                        skybox_path: skybox_path.clone(),
+                    });
+                    for (mut text, _) in &mut path_label_param_set.p1().iter_mut() {
+                        text.0 = format!("[ skybox asset ]: {}", skybox_path)
+                    }
+                    for (mut text, _) in &mut path_label_color_param_set.p1().iter_mut() {
+                        text.0 = Color::srgb(255., 255., 255.);
+                    }
+                }
+            } else if check_json_file(&p) && is_lighting_rig_file(path_buf) {
+                // a lighting rig only replace the lights of the next operation window, it doesn't touch the settings
+                match load_lighting_rig(path_buf) {
+                    Ok(rig) => {
+                        println!("💡 Lighting rig loaded with {} light(s)", rig.lights.len());
+                        lighting_rig.rig = rig;
+                    }
+                    Err(err) => eprintln!("❌ {}", err),
+                }
             } else if check_json_file(&p) {
                 let file = OpenOptions::new()
                     .write(true)
@@ -485,6 +689,9 @@ pub fn file_drag_and_drop_system(
 
                 serde_json::to_writer(file, &new_json_setting).unwrap();
 
+                lighting_rig.rig = settings_lighting_rig(&new_json_setting);
+                *skybox_pool = settings_skybox_pool(&new_json_setting);
+
                 operation_settings.yaw_min_value = new_json_setting.yaw_min_value;
                 operation_settings.yaw_max_value = new_json_setting.yaw_max_value;
                 operation_settings.pitch_min_value = new_json_setting.pitch_min_value;
@@ -497,11 +704,30 @@ pub fn file_drag_and_drop_system(
                     new_json_setting.model_reposition_sensitivity;
                 operation_settings.mouse_sensitivity = new_json_setting.mouse_sensitivity;
                 operation_settings.zoom_sensitivity = new_json_setting.zoom_sensitivity;
+                operation_settings.ground_plane = new_json_setting.ground_plane;
 
+                live_capture_settings.configured_live_capture_iteration =
+                    new_json_setting.live_capture_iteration;
                 live_capture_settings.live_capture_iteration =
                     new_json_setting.live_capture_iteration;
+                live_capture_settings.viewpoint_sampler = new_json_setting.viewpoint_sampler;
+                live_capture_settings.seed = new_json_setting.seed;
+                live_capture_settings.max_snapshots_in_flight =
+                    new_json_setting.max_snapshots_in_flight.max(1);
+
+                live_capture_settings.animation_capture = new_json_setting.animation_capture;
+                live_capture_settings.lighting_randomization =
+                    new_json_setting.lighting_randomization;
+
+                three_d_model_asset_path.gltf_scene = new_json_setting.gltf_scene;
 
                 save_settings.base_dir_path = new_json_setting.image_save_dir;
+
+                capture_channels.depth_output = new_json_setting.depth_output;
+                capture_channels.normal_output = new_json_setting.normal_output;
+                capture_channels.mask_output = new_json_setting.mask_output;
+                capture_channels.rgba_output = new_json_setting.rgba_output;
+                capture_channels.instance_output = new_json_setting.instance_output;
             }
         }
     }
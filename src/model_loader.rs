@@ -0,0 +1,1194 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, LoadContext},
+    prelude::*,
+    render::{
+        mesh::{Indices, PrimitiveTopology},
+        render_asset::RenderAssetUsages,
+    },
+};
+
+// glTF files hold their scenes as labeled assets, the other model formats are loaded as a single scene ( as well as the
+// glTF entries already labeled with the scene to render )
+pub fn load_model_scene(asset_server: &AssetServer, model_path: &str) -> Handle<Scene> {
+    match Path::new(model_path).extension() {
+        Some(ext) if ext == "glb" || ext == "gltf" => {
+            return asset_server.load(GltfAssetLabel::Scene(0).from_asset(model_path.to_string()));
+        }
+        _ => return asset_server.load(model_path.to_string()),
+    }
+}
+
+// Wavefront OBJ, the materials are read from the MTL files next to it
+#[derive(Default)]
+pub struct ObjLoader;
+
+impl AssetLoader for ObjLoader {
+    type Asset = Scene;
+    type Settings = ();
+    type Error = String;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Scene, String> {
+        let bytes = read_model_bytes(reader).await?;
+        let obj = parse_obj(&String::from_utf8_lossy(&bytes))?;
+
+        let model_dir = model_dir(load_context);
+        let mut materials: HashMap<String, Handle<StandardMaterial>> = HashMap::new();
+        for mtl_file in &obj.mtl_files {
+            let mtl_path = model_dir.join(mtl_file);
+            let mtl_bytes = match load_context.read_asset_bytes(mtl_path.clone()).await {
+                Ok(mtl_bytes) => mtl_bytes,
+                Err(err) => {
+                    eprintln!(
+                        "⚠️ Failed to read the material file {:?}: {}",
+                        mtl_path, err
+                    );
+                    continue;
+                }
+            };
+            for (material_name, mtl_material) in parse_mtl(&String::from_utf8_lossy(&mtl_bytes)) {
+                let mut material = default_material();
+                material.base_color = Color::srgba(
+                    mtl_material.diffuse[0],
+                    mtl_material.diffuse[1],
+                    mtl_material.diffuse[2],
+                    mtl_material.alpha,
+                );
+                if mtl_material.alpha < 1.0 {
+                    material.alpha_mode = AlphaMode::Blend;
+                }
+                if let Some(texture) = mtl_material.diffuse_texture {
+                    let texture_path = mtl_path.parent().unwrap_or(Path::new("")).join(texture);
+                    material.base_color_texture = Some(load_context.load(texture_path));
+                }
+                let material_label = format!("Material{}", materials.len());
+                materials.insert(
+                    material_name,
+                    load_context.add_labeled_asset(material_label, material),
+                );
+            }
+        }
+
+        let mut nodes: Vec<ModelNode> = vec![];
+        for group in obj.groups {
+            let material = match materials.get(&group.material) {
+                Some(material) => material.clone(),
+                None => {
+                    let material = if group.data.colors.is_empty() {
+                        default_material()
+                    } else {
+                        vertex_color_material()
+                    };
+                    let material_label = format!("Material{}", materials.len());
+                    let material = load_context.add_labeled_asset(material_label, material);
+                    materials.insert(group.material.clone(), material.clone());
+                    material
+                }
+            };
+            let mesh = ModelMesh {
+                name: group.material,
+                data: group.data,
+                material,
+            };
+            match nodes.iter_mut().find(|node| node.name == group.object) {
+                Some(node) => node.meshes.push(mesh),
+                None => nodes.push(ModelNode {
+                    name: group.object,
+                    meshes: vec![mesh],
+                }),
+            }
+        }
+
+        return build_model_scene(nodes, load_context);
+    }
+
+    fn extensions(&self) -> &[&str] {
+        return &["obj"];
+    }
+}
+
+// STL, binary or ASCII, there is no material in the file so the model is rendered with the default one
+#[derive(Default)]
+pub struct StlLoader;
+
+impl AssetLoader for StlLoader {
+    type Asset = Scene;
+    type Settings = ();
+    type Error = String;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Scene, String> {
+        let bytes = read_model_bytes(reader).await?;
+        let data = parse_stl(&bytes)?;
+        let material = load_context.add_labeled_asset("Material0".to_string(), default_material());
+
+        return build_single_mesh_scene(data, material, load_context);
+    }
+
+    fn extensions(&self) -> &[&str] {
+        return &["stl"];
+    }
+}
+
+// PLY, ASCII or binary, the vertex colours are used when the file has them
+#[derive(Default)]
+pub struct PlyLoader;
+
+impl AssetLoader for PlyLoader {
+    type Asset = Scene;
+    type Settings = ();
+    type Error = String;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Scene, String> {
+        let bytes = read_model_bytes(reader).await?;
+        let data = parse_ply(&bytes)?;
+        let material = if data.colors.is_empty() {
+            default_material()
+        } else {
+            vertex_color_material()
+        };
+        let material = load_context.add_labeled_asset("Material0".to_string(), material);
+
+        return build_single_mesh_scene(data, material, load_context);
+    }
+
+    fn extensions(&self) -> &[&str] {
+        return &["ply"];
+    }
+}
+
+async fn read_model_bytes(reader: &mut dyn Reader) -> Result<Vec<u8>, String> {
+    let mut bytes = vec![];
+    reader
+        .read_to_end(&mut bytes)
+        .await
+        .map_err(|err| err.to_string())?;
+
+    return Ok(bytes);
+}
+
+fn model_dir(load_context: &LoadContext) -> PathBuf {
+    return load_context
+        .path()
+        .parent()
+        .unwrap_or(Path::new(""))
+        .to_path_buf();
+}
+
+fn model_name(load_context: &LoadContext) -> String {
+    return match load_context.path().file_stem() {
+        Some(file_stem) => file_stem.to_string_lossy().to_string(),
+        None => "".to_string(),
+    };
+}
+
+fn default_material() -> StandardMaterial {
+    return StandardMaterial {
+        base_color: Color::srgb(0.8, 0.8, 0.8),
+        perceptual_roughness: 0.6,
+        // scanned and CAD meshes often have an inconsistent winding, so both sides of every triangle are rendered
+        double_sided: true,
+        cull_mode: None,
+        ..default()
+    };
+}
+
+// the vertex colours are multiplied with the base colour
+fn vertex_color_material() -> StandardMaterial {
+    return StandardMaterial {
+        base_color: Color::WHITE,
+        ..default_material()
+    };
+}
+
+// the vertices of one mesh, the normals / uvs / colours are only used when every vertex has one
+#[derive(Default)]
+struct MeshData {
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    uvs: Vec<[f32; 2]>,
+    colors: Vec<[f32; 4]>,
+    indices: Vec<u32>,
+}
+
+impl MeshData {
+    fn into_mesh(self) -> Mesh {
+        let vertex_count = self.positions.len();
+        let has_normals = self.normals.len() == vertex_count;
+
+        let mut mesh = Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        );
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, self.positions);
+        if has_normals {
+            mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals);
+        }
+        if self.uvs.len() == vertex_count {
+            mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, self.uvs);
+        }
+        if self.colors.len() == vertex_count {
+            mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, self.colors);
+        }
+        mesh.insert_indices(Indices::U32(self.indices));
+        if !has_normals {
+            mesh.compute_smooth_normals();
+        }
+
+        return mesh;
+    }
+}
+
+struct ModelMesh {
+    name: String,
+    data: MeshData,
+    material: Handle<StandardMaterial>,
+}
+
+struct ModelNode {
+    name: String,
+    meshes: Vec<ModelMesh>,
+}
+
+// spawn a node per object with a child per mesh, the same hierarchy as a glTF scene so that the instance legend get the
+// object and material names
+fn build_model_scene(
+    nodes: Vec<ModelNode>,
+    load_context: &mut LoadContext,
+) -> Result<Scene, String> {
+    if nodes
+        .iter()
+        .all(|node| node.meshes.iter().all(|mesh| mesh.data.indices.is_empty()))
+    {
+        return Err(format!("{:?} has no face to render", load_context.path()));
+    }
+
+    let mut world = World::default();
+    let mut mesh_count = 0;
+    for node in nodes {
+        let mut meshes = vec![];
+        for mesh in node.meshes {
+            if mesh.data.indices.is_empty() {
+                continue;
+            }
+            let mesh_label = format!("Mesh{}", mesh_count);
+            mesh_count += 1;
+            meshes.push((
+                mesh.name,
+                load_context.add_labeled_asset(mesh_label, mesh.data.into_mesh()),
+                mesh.material,
+            ));
+        }
+        world
+            .spawn((
+                Name::new(node.name),
+                Transform::default(),
+                Visibility::default(),
+            ))
+            .with_children(|parent| {
+                for (mesh_name, mesh, material) in meshes {
+                    parent.spawn((Name::new(mesh_name), Mesh3d(mesh), MeshMaterial3d(material)));
+                }
+            });
+    }
+
+    return Ok(Scene::new(world));
+}
+
+fn build_single_mesh_scene(
+    data: MeshData,
+    material: Handle<StandardMaterial>,
+    load_context: &mut LoadContext,
+) -> Result<Scene, String> {
+    let name = model_name(load_context);
+    return build_model_scene(
+        vec![ModelNode {
+            name: name.clone(),
+            meshes: vec![ModelMesh {
+                name,
+                data,
+                material,
+            }],
+        }],
+        load_context,
+    );
+}
+
+// the faces of one object using one material
+struct ObjGroup {
+    object: String,
+    material: String,
+    data: MeshData,
+    // the OBJ position / uv / normal indices of a corner to the mesh vertex, so that the corners sharing them share the
+    // vertex
+    vertices: HashMap<(usize, Option<usize>, Option<usize>), u32>,
+}
+
+struct ObjFile {
+    mtl_files: Vec<String>,
+    groups: Vec<ObjGroup>,
+}
+
+fn parse_obj(text: &str) -> Result<ObjFile, String> {
+    let mut positions: Vec<[f32; 3]> = vec![];
+    let mut colors: Vec<[f32; 4]> = vec![];
+    let mut uvs: Vec<[f32; 2]> = vec![];
+    let mut normals: Vec<[f32; 3]> = vec![];
+    let mut mtl_files: Vec<String> = vec![];
+    let mut groups: Vec<ObjGroup> = vec![];
+    let mut object = "".to_string();
+    let mut material = "".to_string();
+
+    for (line_number, line) in text.lines().enumerate() {
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let values: Vec<&str> = tokens.collect();
+        let line_error = |err: String| format!("line {}: {}", line_number + 1, err);
+
+        match keyword {
+            "v" => {
+                let v = parse_floats(&values).map_err(line_error)?;
+                if v.len() < 3 {
+                    return Err(line_error("a vertex needs 3 coordinates".to_string()));
+                }
+                positions.push([v[0], v[1], v[2]]);
+                // some scanners write the vertex colour after the position
+                if v.len() >= 6 {
+                    colors.push(Color::srgb(v[3], v[4], v[5]).to_linear().to_f32_array());
+                } else {
+                    colors.push([1.0, 1.0, 1.0, 1.0]);
+                }
+            }
+            "vt" => {
+                let vt = parse_floats(&values).map_err(line_error)?;
+                if vt.len() < 2 {
+                    return Err(line_error(
+                        "a texture coordinate needs 2 values".to_string(),
+                    ));
+                }
+                // OBJ texture coordinates start from the bottom of the image
+                uvs.push([vt[0], 1.0 - vt[1]]);
+            }
+            "vn" => {
+                let vn = parse_floats(&values).map_err(line_error)?;
+                if vn.len() < 3 {
+                    return Err(line_error("a normal needs 3 values".to_string()));
+                }
+                normals.push([vn[0], vn[1], vn[2]]);
+            }
+            "f" => {
+                let mut corners = vec![];
+                for value in &values {
+                    let mut indices = value.split('/');
+                    let position = resolve_obj_index(indices.next(), positions.len())
+                        .map_err(line_error)?
+                        .ok_or(line_error("a face corner needs a vertex".to_string()))?;
+                    let uv = resolve_obj_index(indices.next(), uvs.len()).map_err(line_error)?;
+                    let normal =
+                        resolve_obj_index(indices.next(), normals.len()).map_err(line_error)?;
+                    corners.push((position, uv, normal));
+                }
+                if corners.len() < 3 {
+                    continue;
+                }
+
+                let group_index = match groups
+                    .iter()
+                    .position(|group| group.object == object && group.material == material)
+                {
+                    Some(group_index) => group_index,
+                    None => {
+                        groups.push(ObjGroup {
+                            object: object.clone(),
+                            material: material.clone(),
+                            data: MeshData::default(),
+                            vertices: HashMap::new(),
+                        });
+                        groups.len() - 1
+                    }
+                };
+                let group = &mut groups[group_index];
+
+                let mut corner_vertices = vec![];
+                for corner in corners {
+                    let vertex = match group.vertices.get(&corner) {
+                        Some(vertex) => *vertex,
+                        None => {
+                            let vertex = group.data.positions.len() as u32;
+                            group.data.positions.push(positions[corner.0]);
+                            group.data.colors.push(colors[corner.0]);
+                            if let Some(uv) = corner.1 {
+                                group.data.uvs.push(uvs[uv]);
+                            }
+                            if let Some(normal) = corner.2 {
+                                group.data.normals.push(normals[normal]);
+                            }
+                            group.vertices.insert(corner, vertex);
+                            vertex
+                        }
+                    };
+                    corner_vertices.push(vertex);
+                }
+                // polygons are split into a fan of triangles
+                for i in 1..corner_vertices.len() - 1 {
+                    group.data.indices.extend([
+                        corner_vertices[0],
+                        corner_vertices[i],
+                        corner_vertices[i + 1],
+                    ]);
+                }
+            }
+            "o" | "g" => object = values.join(" "),
+            "usemtl" => material = values.join(" "),
+            "mtllib" => mtl_files.push(values.join(" ")),
+            _ => {}
+        }
+    }
+
+    // the colours are only kept when the file has some
+    let has_colors = colors.iter().any(|color| *color != [1.0, 1.0, 1.0, 1.0]);
+    for group in groups.iter_mut() {
+        if !has_colors {
+            group.data.colors = vec![];
+        }
+    }
+
+    return Ok(ObjFile { mtl_files, groups });
+}
+
+// OBJ indices start from 1, a negative one is relative to the end of the list
+fn resolve_obj_index(value: Option<&str>, count: usize) -> Result<Option<usize>, String> {
+    let value = match value {
+        Some(value) if !value.is_empty() => value,
+        _ => return Ok(None),
+    };
+    let index: i64 = value
+        .parse()
+        .map_err(|_| format!("invalid index {}", value))?;
+    let resolved = if index < 0 {
+        count as i64 + index
+    } else {
+        index - 1
+    };
+    if resolved < 0 || resolved >= count as i64 {
+        return Err(format!("index {} out of range", index));
+    }
+
+    return Ok(Some(resolved as usize));
+}
+
+fn parse_floats(values: &[&str]) -> Result<Vec<f32>, String> {
+    return values
+        .iter()
+        .map(|value| {
+            value
+                .parse::<f32>()
+                .map_err(|_| format!("invalid number {}", value))
+        })
+        .collect();
+}
+
+struct MtlMaterial {
+    diffuse: [f32; 3],
+    alpha: f32,
+    diffuse_texture: Option<String>,
+}
+
+fn parse_mtl(text: &str) -> Vec<(String, MtlMaterial)> {
+    let mut materials: Vec<(String, MtlMaterial)> = vec![];
+
+    for line in text.lines() {
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let values: Vec<&str> = tokens.collect();
+
+        if keyword == "newmtl" {
+            materials.push((
+                values.join(" "),
+                MtlMaterial {
+                    diffuse: [0.8, 0.8, 0.8],
+                    alpha: 1.0,
+                    diffuse_texture: None,
+                },
+            ));
+            continue;
+        }
+        let material = match materials.last_mut() {
+            Some((_, material)) => material,
+            None => continue,
+        };
+        match keyword {
+            "Kd" => {
+                if let Ok(kd) = parse_floats(&values) {
+                    if kd.len() >= 3 {
+                        material.diffuse = [kd[0], kd[1], kd[2]];
+                    }
+                }
+            }
+            "d" => {
+                if let Ok(d) = parse_floats(&values) {
+                    if let Some(d) = d.first() {
+                        material.alpha = *d;
+                    }
+                }
+            }
+            "Tr" => {
+                if let Ok(tr) = parse_floats(&values) {
+                    if let Some(tr) = tr.first() {
+                        material.alpha = 1.0 - *tr;
+                    }
+                }
+            }
+            // the texture options are skipped, the file name is the last value
+            "map_Kd" => material.diffuse_texture = values.last().map(|value| value.to_string()),
+            _ => {}
+        }
+    }
+
+    return materials;
+}
+
+fn parse_stl(bytes: &[u8]) -> Result<MeshData, String> {
+    let mut data = MeshData::default();
+
+    // a binary STL is an 80 bytes header, the triangle count and 50 bytes per triangle, its header can also start with
+    // "solid" so the size is checked first
+    let binary_triangle_count = if bytes.len() >= 84 {
+        u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]) as usize
+    } else {
+        0
+    };
+    if bytes.len() >= 84 && bytes.len() == 84 + binary_triangle_count * 50 {
+        for triangle in bytes[84..].chunks_exact(50) {
+            // skip the facet normal, most exporters leave it empty
+            for vertex in triangle[12..48].chunks_exact(12) {
+                data.positions.push([
+                    f32::from_le_bytes([vertex[0], vertex[1], vertex[2], vertex[3]]),
+                    f32::from_le_bytes([vertex[4], vertex[5], vertex[6], vertex[7]]),
+                    f32::from_le_bytes([vertex[8], vertex[9], vertex[10], vertex[11]]),
+                ]);
+            }
+        }
+    } else {
+        let text = String::from_utf8_lossy(bytes);
+        let mut tokens = text.split_whitespace();
+        while let Some(token) = tokens.next() {
+            if token != "vertex" {
+                continue;
+            }
+            let mut position = [0.0; 3];
+            for value in position.iter_mut() {
+                let token = tokens.next().unwrap_or("");
+                *value = token
+                    .parse()
+                    .map_err(|_| format!("invalid number {}", token))?;
+            }
+            data.positions.push(position);
+        }
+        if data.positions.len() % 3 != 0 {
+            return Err("the number of vertices is not a multiple of 3".to_string());
+        }
+    }
+
+    // every triangle has its own vertices, the computed normals are the face normals
+    data.indices = (0..data.positions.len() as u32).collect();
+
+    return Ok(data);
+}
+
+#[derive(PartialEq)]
+enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+struct PlyProperty {
+    name: String,
+    data_type: String,
+    // the type of the item count for a list property
+    count_type: Option<String>,
+}
+
+struct PlyElement {
+    name: String,
+    count: usize,
+    properties: Vec<PlyProperty>,
+}
+
+// read the values of the body one after another, whatever the format is
+struct PlyReader<'a> {
+    format: PlyFormat,
+    bytes: &'a [u8],
+    position: usize,
+    tokens: std::str::SplitAsciiWhitespace<'a>,
+}
+
+impl PlyReader<'_> {
+    fn read(&mut self, data_type: &str) -> Result<f64, String> {
+        if self.format == PlyFormat::Ascii {
+            let token = self
+                .tokens
+                .next()
+                .ok_or("unexpected end of file".to_string())?;
+            return token
+                .parse()
+                .map_err(|_| format!("invalid number {}", token));
+        }
+
+        let size = match data_type {
+            "char" | "int8" | "uchar" | "uint8" => 1,
+            "short" | "int16" | "ushort" | "uint16" => 2,
+            "int" | "int32" | "uint" | "uint32" | "float" | "float32" => 4,
+            "double" | "float64" => 8,
+            _ => return Err(format!("unknown property type {}", data_type)),
+        };
+        if self.position + size > self.bytes.len() {
+            return Err("unexpected end of file".to_string());
+        }
+        let mut value = [0u8; 8];
+        value[..size].copy_from_slice(&self.bytes[self.position..self.position + size]);
+        self.position += size;
+        if self.format == PlyFormat::BinaryBigEndian {
+            value[..size].reverse();
+        }
+
+        return Ok(match data_type {
+            "char" | "int8" => value[0] as i8 as f64,
+            "uchar" | "uint8" => value[0] as f64,
+            "short" | "int16" => i16::from_le_bytes([value[0], value[1]]) as f64,
+            "ushort" | "uint16" => u16::from_le_bytes([value[0], value[1]]) as f64,
+            "int" | "int32" => i32::from_le_bytes([value[0], value[1], value[2], value[3]]) as f64,
+            "uint" | "uint32" => {
+                u32::from_le_bytes([value[0], value[1], value[2], value[3]]) as f64
+            }
+            "float" | "float32" => {
+                f32::from_le_bytes([value[0], value[1], value[2], value[3]]) as f64
+            }
+            _ => f64::from_le_bytes(value),
+        });
+    }
+}
+
+// the maximum value of an integer colour channel, floating point colours are already between 0 and 1
+fn ply_color_scale(data_type: &str) -> f32 {
+    return match data_type {
+        "uchar" | "uint8" => 255.0,
+        "ushort" | "uint16" => 65535.0,
+        _ => 1.0,
+    };
+}
+
+fn parse_ply(bytes: &[u8]) -> Result<MeshData, String> {
+    let header_end = match bytes
+        .windows(b"end_header".len())
+        .position(|window| window == b"end_header")
+    {
+        Some(header_end) => header_end,
+        None => return Err("missing PLY header".to_string()),
+    };
+    let header = String::from_utf8_lossy(&bytes[..header_end]);
+    // the body start after the end of the "end_header" line
+    let mut body_start = header_end + b"end_header".len();
+    while body_start < bytes.len() && bytes[body_start] != b'\n' {
+        body_start += 1;
+    }
+    body_start = (body_start + 1).min(bytes.len());
+
+    let mut format = None;
+    let mut elements: Vec<PlyElement> = vec![];
+    for line in header.lines() {
+        let values: Vec<&str> = line.split_whitespace().collect();
+        match values.as_slice() {
+            ["format", "ascii", ..] => format = Some(PlyFormat::Ascii),
+            ["format", "binary_little_endian", ..] => format = Some(PlyFormat::BinaryLittleEndian),
+            ["format", "binary_big_endian", ..] => format = Some(PlyFormat::BinaryBigEndian),
+            ["element", name, count] => elements.push(PlyElement {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| format!("invalid element count {}", count))?,
+                properties: vec![],
+            }),
+            ["property", "list", count_type, data_type, name] => match elements.last_mut() {
+                Some(element) => element.properties.push(PlyProperty {
+                    name: name.to_string(),
+                    data_type: data_type.to_string(),
+                    count_type: Some(count_type.to_string()),
+                }),
+                None => return Err("property declared before any element".to_string()),
+            },
+            ["property", data_type, name] => match elements.last_mut() {
+                Some(element) => element.properties.push(PlyProperty {
+                    name: name.to_string(),
+                    data_type: data_type.to_string(),
+                    count_type: None,
+                }),
+                None => return Err("property declared before any element".to_string()),
+            },
+            _ => {}
+        }
+    }
+    let format = match format {
+        Some(format) => format,
+        None => return Err("missing or unknown PLY format".to_string()),
+    };
+
+    let body = &bytes[body_start..];
+    let mut reader = PlyReader {
+        format,
+        bytes: body,
+        position: 0,
+        tokens: std::str::from_utf8(body)
+            .unwrap_or("")
+            .split_ascii_whitespace(),
+    };
+    let mut data = MeshData::default();
+    for element in &elements {
+        for _ in 0..element.count {
+            let mut position = [0.0; 3];
+            let mut normal = [0.0; 3];
+            let mut color = [1.0; 4];
+            let (mut has_normal, mut has_color) = (false, false);
+
+            for property in &element.properties {
+                if let Some(count_type) = &property.count_type {
+                    let count = reader.read(count_type)? as usize;
+                    let mut list = vec![];
+                    for _ in 0..count {
+                        list.push(reader.read(&property.data_type)? as u32);
+                    }
+                    if element.name == "face"
+                        && (property.name == "vertex_indices" || property.name == "vertex_index")
+                        && list.len() >= 3
+                    {
+                        // polygons are split into a fan of triangles
+                        for i in 1..list.len() - 1 {
+                            data.indices.extend([list[0], list[i], list[i + 1]]);
+                        }
+                    }
+                    continue;
+                }
+
+                let value = reader.read(&property.data_type)? as f32;
+                if element.name != "vertex" {
+                    continue;
+                }
+                let color_value = value / ply_color_scale(&property.data_type);
+                match property.name.as_str() {
+                    "x" => position[0] = value,
+                    "y" => position[1] = value,
+                    "z" => position[2] = value,
+                    "nx" => (normal[0], has_normal) = (value, true),
+                    "ny" => (normal[1], has_normal) = (value, true),
+                    "nz" => (normal[2], has_normal) = (value, true),
+                    "red" | "diffuse_red" => (color[0], has_color) = (color_value, true),
+                    "green" | "diffuse_green" => (color[1], has_color) = (color_value, true),
+                    "blue" | "diffuse_blue" => (color[2], has_color) = (color_value, true),
+                    "alpha" => (color[3], has_color) = (color_value, true),
+                    _ => {}
+                }
+            }
+
+            if element.name == "vertex" {
+                data.positions.push(position);
+                if has_normal {
+                    data.normals.push(normal);
+                }
+                if has_color {
+                    // the colours of the file are in sRGB, the mesh ones are linear
+                    data.colors.push(
+                        Color::srgba(color[0], color[1], color[2], color[3])
+                            .to_linear()
+                            .to_f32_array(),
+                    );
+                }
+            }
+        }
+    }
+
+    if let Some(index) = data
+        .indices
+        .iter()
+        .find(|index| **index as usize >= data.positions.len())
+    {
+        return Err(format!("vertex index {} out of range", index));
+    }
+
+    return Ok(data);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_single_group_obj(text: &str) -> MeshData {
+        let mut obj = parse_obj(text).unwrap();
+        assert_eq!(obj.groups.len(), 1);
+        return obj.groups.remove(0).data;
+    }
+
+    #[test]
+    fn obj_negative_indices_are_relative_to_the_end() {
+        let data =
+            parse_single_group_obj("v 0 0 0\nv 9 9 9\nv 1 0 0\nv 0 1 0\nv 0 0 1\nf -3 -2 -1\n");
+
+        assert_eq!(
+            data.positions,
+            vec![[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]
+        );
+        assert_eq!(data.indices, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn obj_polygons_are_split_into_a_fan() {
+        let data =
+            parse_single_group_obj("v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nv 0 2 0\nf 1 2 3 4 5\n");
+
+        assert_eq!(data.positions.len(), 5);
+        assert_eq!(data.indices, vec![0, 1, 2, 0, 2, 3, 0, 3, 4]);
+    }
+
+    #[test]
+    fn obj_corners_share_a_vertex_only_with_the_same_indices() {
+        let data = parse_single_group_obj(
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 1 0\nvt 0 0\nvt 1 1\nvn 0 0 1\n\
+             f 1/1/1 2/1/1 3/1/1\nf 2/1/1 4/1/1 3/2/1\n",
+        );
+
+        // the third corner of the second face use another uv, so it is a new vertex
+        assert_eq!(data.positions.len(), 5);
+        assert_eq!(data.indices, vec![0, 1, 2, 1, 3, 4]);
+        // the v of OBJ start from the bottom of the image
+        assert_eq!(data.uvs[4], [1.0, 0.0]);
+        assert_eq!(data.normals.len(), 5);
+    }
+
+    #[test]
+    fn obj_groups_split_by_object_and_material() {
+        let obj = parse_obj(
+            "mtllib scene.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\n\
+             o first\nusemtl red\nf 1 2 3\nusemtl blue\nf 1 2 3\no second\nusemtl red\nf 1 2 3\n",
+        )
+        .unwrap();
+
+        assert_eq!(obj.mtl_files, vec!["scene.mtl".to_string()]);
+        let groups: Vec<(&str, &str)> = obj
+            .groups
+            .iter()
+            .map(|group| (group.object.as_str(), group.material.as_str()))
+            .collect();
+        assert_eq!(
+            groups,
+            vec![("first", "red"), ("first", "blue"), ("second", "red")]
+        );
+    }
+
+    #[test]
+    fn obj_vertex_colors_are_only_kept_when_present() {
+        let plain = parse_single_group_obj("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n");
+        assert!(plain.colors.is_empty());
+
+        let colored =
+            parse_single_group_obj("v 0 0 0 1 0 0\nv 1 0 0 0 1 0\nv 0 1 0 0 0 1\nf 1 2 3\n");
+        assert_eq!(colored.colors[0], [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(colored.colors[2], [0.0, 0.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn obj_malformed_files_report_the_line() {
+        let cases = [
+            ("v 0 0\n", "line 1: a vertex needs 3 coordinates"),
+            ("v 0 0 x\n", "line 1: invalid number x"),
+            ("vt 0\n", "line 1: a texture coordinate needs 2 values"),
+            ("vn 0 0\n", "line 1: a normal needs 3 values"),
+            ("v 0 0 0\n\nf 1 1 2\n", "line 3: index 2 out of range"),
+            ("v 0 0 0\nf 0 1 1\n", "line 2: index 0 out of range"),
+            ("v 0 0 0\nf -2 1 1\n", "line 2: index -2 out of range"),
+            ("v 0 0 0\nf a 1 1\n", "line 2: invalid index a"),
+            (
+                "v 0 0 0\nf /1 1 1\n",
+                "line 2: a face corner needs a vertex",
+            ),
+            ("v 0 0 0\nf 1/1 1 1\n", "line 2: index 1 out of range"),
+        ];
+
+        for (text, error) in cases {
+            assert_eq!(parse_obj(text).err(), Some(error.to_string()), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn mtl_materials_read_the_diffuse_and_alpha() {
+        let materials = parse_mtl(
+            "Kd 0 0 0\nnewmtl red\nKd 1 0 0\nd 0.5\nmap_Kd -s 1 1 1 red.png\nnewmtl glass\nTr 0.75\n",
+        );
+
+        assert_eq!(materials.len(), 2);
+        assert_eq!(materials[0].0, "red");
+        assert_eq!(materials[0].1.diffuse, [1.0, 0.0, 0.0]);
+        assert_eq!(materials[0].1.alpha, 0.5);
+        assert_eq!(materials[0].1.diffuse_texture, Some("red.png".to_string()));
+        assert_eq!(materials[1].1.diffuse, [0.8, 0.8, 0.8]);
+        assert_eq!(materials[1].1.alpha, 0.25);
+    }
+
+    fn binary_stl(header: &[u8], triangles: &[[[f32; 3]; 3]]) -> Vec<u8> {
+        let mut bytes = vec![0u8; 80];
+        bytes[..header.len()].copy_from_slice(header);
+        bytes.extend((triangles.len() as u32).to_le_bytes());
+        for triangle in triangles {
+            bytes.extend([0u8; 12]);
+            for value in triangle.iter().flatten() {
+                bytes.extend(value.to_le_bytes());
+            }
+            bytes.extend([0u8; 2]);
+        }
+        return bytes;
+    }
+
+    #[test]
+    fn stl_binary_is_detected_by_its_size_even_with_a_solid_header() {
+        let triangle = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+        let data = parse_stl(&binary_stl(b"solid exported", &[triangle, triangle])).unwrap();
+
+        assert_eq!(data.positions.len(), 6);
+        assert_eq!(data.positions[1], [1.0, 0.0, 0.0]);
+        assert_eq!(data.indices, vec![0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn stl_ascii_reads_every_vertex() {
+        let data = parse_stl(
+            b"solid test\nfacet normal 0 0 1\nouter loop\nvertex 0 0 0\nvertex 1 0 0\nvertex 0 1.5 0\nendloop\nendfacet\nendsolid test\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            data.positions,
+            vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.5, 0.0]]
+        );
+        assert_eq!(data.indices, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn stl_malformed_files_are_rejected() {
+        assert_eq!(
+            parse_stl(b"solid test\nvertex 0 0 0\nvertex 1 0 0\n").err(),
+            Some("the number of vertices is not a multiple of 3".to_string())
+        );
+        assert_eq!(
+            parse_stl(b"solid test\nvertex 0 zero 0\n").err(),
+            Some("invalid number zero".to_string())
+        );
+        assert_eq!(
+            parse_stl(b"solid test\nvertex 0 0").err(),
+            Some("invalid number ".to_string())
+        );
+
+        // a binary file cut short is no longer the size of its triangles and is read as an invalid ASCII one
+        let triangle = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+        let mut bytes = binary_stl(b"", &[triangle]);
+        bytes.truncate(100);
+        assert!(parse_stl(&bytes).unwrap().positions.is_empty());
+    }
+
+    fn ply(header: &str, body: &[u8]) -> Vec<u8> {
+        let mut bytes = header.as_bytes().to_vec();
+        bytes.extend(body);
+        return bytes;
+    }
+
+    #[test]
+    fn ply_ascii_reads_vertices_colors_and_faces() {
+        let data = parse_ply(&ply(
+            "ply\nformat ascii 1.0\ncomment test\nelement vertex 4\nproperty float x\nproperty float y\nproperty float z\n\
+             property uchar red\nproperty uchar green\nproperty uchar blue\nelement face 1\n\
+             property list uchar int vertex_indices\nend_header\n",
+            b"0 0 0 255 0 0\n1 0 0 0 255 0\n1 1 0 0 0 255\n0 1 0 255 255 255\n4 0 1 2 3\n",
+        ))
+        .unwrap();
+
+        assert_eq!(data.positions[2], [1.0, 1.0, 0.0]);
+        assert!(data.normals.is_empty());
+        assert_eq!(data.colors[0], [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(data.colors[3], [1.0, 1.0, 1.0, 1.0]);
+        assert_eq!(data.indices, vec![0, 1, 2, 0, 2, 3]);
+    }
+
+    #[test]
+    fn ply_binary_little_endian() {
+        let mut body = vec![];
+        for (position, normal) in [
+            ([0.0f32, 0.0, 0.0], [0.0f32, 0.0, 1.0]),
+            ([1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+            ([0.0, 1.0, 0.0], [0.0, 0.0, 1.0]),
+        ] {
+            for value in position.iter().chain(normal.iter()) {
+                body.extend(value.to_le_bytes());
+            }
+        }
+        body.push(3);
+        for index in [0u32, 1, 2] {
+            body.extend(index.to_le_bytes());
+        }
+
+        let data = parse_ply(&ply(
+            "ply\r\nformat binary_little_endian 1.0\r\nelement vertex 3\r\nproperty float x\r\nproperty float y\r\n\
+             property float z\r\nproperty float nx\r\nproperty float ny\r\nproperty float nz\r\nelement face 1\r\n\
+             property list uchar uint vertex_index\r\nend_header\r\n",
+            &body,
+        ))
+        .unwrap();
+
+        assert_eq!(
+            data.positions,
+            vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]
+        );
+        assert_eq!(data.normals, vec![[0.0, 0.0, 1.0]; 3]);
+        assert_eq!(data.indices, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn ply_binary_big_endian_reads_every_property_type() {
+        let mut body = vec![];
+        // x double, y int8, z int16, w int32 (ignored), red uint16, alpha uchar, confidence uint32 (ignored)
+        for (x, y, z) in [(0.5f64, -1i8, 2i16), (1.0, 3, -4), (2.0, 0, 0)] {
+            body.extend(x.to_be_bytes());
+            body.extend(y.to_be_bytes());
+            body.extend(z.to_be_bytes());
+            body.extend((-7i32).to_be_bytes());
+            body.extend(65535u16.to_be_bytes());
+            body.push(0);
+            body.extend(7u32.to_be_bytes());
+        }
+        body.extend(3u16.to_be_bytes());
+        for index in [2i32, 1, 0] {
+            body.extend(index.to_be_bytes());
+        }
+
+        let data = parse_ply(&ply(
+            "ply\nformat binary_big_endian 1.0\nelement vertex 3\nproperty float64 x\nproperty char y\n\
+             property short z\nproperty int w\nproperty ushort red\nproperty uint8 alpha\nproperty uint confidence\n\
+             element face 1\nproperty list ushort int32 vertex_indices\nend_header\n",
+            &body,
+        ))
+        .unwrap();
+
+        assert_eq!(
+            data.positions,
+            vec![[0.5, -1.0, 2.0], [1.0, 3.0, -4.0], [2.0, 0.0, 0.0]]
+        );
+        assert_eq!(data.colors[0], [1.0, 1.0, 1.0, 0.0]);
+        assert_eq!(data.indices, vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn ply_skips_the_elements_it_does_not_use() {
+        let data = parse_ply(&ply(
+            "ply\nformat ascii 1.0\nelement camera 1\nproperty float view_x\nproperty list uchar float params\n\
+             element vertex 3\nproperty float x\nproperty float y\nproperty float z\nend_header\n",
+            b"9 2 8 8\n0 0 0\n1 0 0\n0 1 0\n",
+        ))
+        .unwrap();
+
+        assert_eq!(data.positions.len(), 3);
+        assert!(data.indices.is_empty());
+    }
+
+    #[test]
+    fn ply_malformed_files_are_rejected() {
+        let vertex_header =
+            "element vertex 1\nproperty float x\nproperty float y\nproperty float z\n";
+        let cases: Vec<(Vec<u8>, &str)> = vec![
+            (b"ply\nformat ascii 1.0\n".to_vec(), "missing PLY header"),
+            (
+                ply(&format!("ply\n{}end_header\n", vertex_header), b"0 0 0\n"),
+                "missing or unknown PLY format",
+            ),
+            (
+                ply("ply\nformat binary_middle_endian 1.0\nend_header\n", b""),
+                "missing or unknown PLY format",
+            ),
+            (
+                ply("ply\nformat ascii 1.0\nproperty float x\nend_header\n", b""),
+                "property declared before any element",
+            ),
+            (
+                ply("ply\nformat ascii 1.0\nelement vertex many\nend_header\n", b""),
+                "invalid element count many",
+            ),
+            (
+                ply(
+                    &format!("ply\nformat ascii 1.0\n{}end_header\n", vertex_header),
+                    b"0 0\n",
+                ),
+                "unexpected end of file",
+            ),
+            (
+                ply(
+                    &format!("ply\nformat ascii 1.0\n{}end_header\n", vertex_header),
+                    b"0 zero 0\n",
+                ),
+                "invalid number zero",
+            ),
+            (
+                ply(
+                    &format!("ply\nformat binary_little_endian 1.0\n{}end_header\n", vertex_header),
+                    &[0u8; 10],
+                ),
+                "unexpected end of file",
+            ),
+            (
+                ply(
+                    "ply\nformat binary_little_endian 1.0\nelement vertex 1\nproperty half x\nend_header\n",
+                    &[0u8; 2],
+                ),
+                "unknown property type half",
+            ),
+            (
+                ply(
+                    &format!(
+                        "ply\nformat ascii 1.0\n{}element face 1\nproperty list uchar int vertex_indices\nend_header\n",
+                        vertex_header
+                    ),
+                    b"0 0 0\n3 0 0 1\n",
+                ),
+                "vertex index 1 out of range",
+            ),
+        ];
+
+        for (bytes, error) in cases {
+            assert_eq!(
+                parse_ply(&bytes).err(),
+                Some(error.to_string()),
+                "{}",
+                String::from_utf8_lossy(&bytes)
+            );
+        }
+    }
+}
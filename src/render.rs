@@ -4,18 +4,27 @@ use bevy::{
     render::{
         camera::RenderTarget,
         render_resource::{TextureViewDescriptor, TextureViewDimension},
+        view::RenderLayers,
     },
     window::{WindowRef, WindowResolution},
 };
 
 use crate::{
     components::{ModelRotateReposition, OrbitCamera},
+    ground::GROUND_RENDER_LAYER,
+    lighting::spawn_lighting_rig,
+    model_loader::load_model_scene,
     resource::{
-        AssetPath, OperationSettings, OperationWindowRelatedEntities, SavePathList, SkyboxAttribute,
+        AssetPath, LightingRigSettings, OperationSettings, OperationWindowRelatedEntities,
+        SavePathList, SkyboxAttribute, SkyboxPool,
     },
+    sampler::frame_rng,
     states::{AppState, CameraFovInitializedState, OperationState, RenderModelForwardOrBackward},
 };
 
+// the random stream of the skybox pool
+const SKYBOX_STREAM: u64 = 0x534B_5942_4F58_504C;
+
 // this will be the function responsible to spawn a window for the 3d model to render in
 pub fn interactive(
     mut commands: Commands,
@@ -25,6 +34,7 @@ pub fn interactive(
     skybox_attributes: Res<SkyboxAttribute>,
     mut operation_window: ResMut<OperationWindowRelatedEntities>,
     operation_settings: Res<OperationSettings>,
+    lighting_rig: Res<LightingRigSettings>,
 ) {
     // spawn a new window ( In MVC, there will be a maximum of 2 window at the same time, 1 for MVC main menu and the other will be for 3d model )
     let interac_window = commands
@@ -38,15 +48,7 @@ pub fn interactive(
 
     let image = images.get_mut(skybox_attributes.skybox_handler.as_ref().unwrap());
     if let Some(image) = image {
-        // get the loaded image back and process it so that it can be compatible for a 3d dimension
-        if image.texture_descriptor.array_layer_count() == 1 {
-            image.reinterpret_stacked_2d_as_array((image.height() / image.width()).max(1));
-            image.texture_view_descriptor = Some(TextureViewDescriptor {
-                dimension: Some(TextureViewDimension::Cube),
-
-                ..Default::default()
-            });
-        }
+        prepare_skybox_image(image);
     }
 
     // Spawn the camera for the 3d model window
@@ -60,7 +62,7 @@ pub fn interactive(
                     .load("embedded://mvc/assets/pisa_diffuse_rgb9e5_zstd.ktx2"), // load the environment map light from embedded resource
                 specular_map: asset_server
                     .load("embedded://mvc/assets/pisa_specular_rgb9e5_zstd.ktx2"), // load the environment map light from embedded resource
-                intensity: 250.0,
+                intensity: lighting_rig.rig.environment_map_intensity,
                 ..default()
             },
             // IMPORTANT, we need to tell the camera where to target
@@ -79,6 +81,8 @@ pub fn interactive(
                 brightness: 1000.0,
                 ..default()
             },
+            // the model and the ground plane
+            RenderLayers::from_layers(&[0, GROUND_RENDER_LAYER]),
         ))
         // this will be relavent for use to control the orbiting of the model
         .insert(OrbitCamera {
@@ -91,66 +95,15 @@ pub fn interactive(
         .insert(Transform::from_scale(Vec3::new(0.5, 0.5, 0.5)))
         .id();
 
-    // light from top
-    let top_light = commands
-        .spawn(
-    (    
-                DirectionalLight {
-                    illuminance: 800.0,
-                    shadows_enabled: false,
-                    ..default()
-                },
-                Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, 0.0, 0.0, 0.0)), // Points along -Z (default)
-            )
-        ).id();
-
-    // light from front
-    let front_light = commands
-        .spawn(
-    (    
-                DirectionalLight {
-                    illuminance: 800.0,
-                    shadows_enabled: false,
-                    ..default()
-                },
-                Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, -std::f32::consts::FRAC_PI_2, 0.0, 0.0)), // -90 deg
-            )
-        ).id();
-
-    // light from top right
-    let top_right_light =  commands
-        .spawn(
-    (    
-                DirectionalLight {
-                    illuminance: 800.0,
-                    shadows_enabled: false,
-                    ..default()
-                },
-                Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, -std::f32::consts::FRAC_PI_4, std::f32::consts::FRAC_PI_4, 0.0)), // -45° X, +45° Y
-            )
-        ).id();
-    
-    // light from top left
-    let top_left_light = commands
-        .spawn(
-    (    
-                DirectionalLight {
-                    illuminance: 800.0,
-                    shadows_enabled: false,
-                    ..default()
-                },
-                Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, -std::f32::consts::FRAC_PI_4, -std::f32::consts::FRAC_PI_4, 0.0)), // -45° X, -45° Y
-            )
-        ).id();
-        
-    // ambient light to fill in any unlit areas
-    commands.insert_resource(AmbientLight {
-            color: Color::WHITE,
-            brightness: 800.0,
-        });
+    // the lights of the rig ( the studio lighting unless the settings give another rig )
+    let light_entities = spawn_lighting_rig(
+        &mut commands,
+        &lighting_rig.rig,
+        operation_settings.ground_plane.is_some(),
+    );
 
     // the scene handler
-    let scene_handler = asset_server.load(GltfAssetLabel::Scene(0).from_asset(model_path.clone()));
+    let scene_handler = load_model_scene(&asset_server, &model_path);
 
     // spawn the 3d model
     let scene_entity = commands
@@ -176,15 +129,8 @@ pub fn interactive(
         .spawn((node, TargetCamera(interac_window_camera)))
         .id();
 
-    let entities_list: Vec<Entity> = vec![
-        interac_window,
-        interac_window_camera,
-        top_light,
-        front_light,
-        top_right_light,
-        top_left_light,
-        node_entity,
-    ];
+    let mut entities_list: Vec<Entity> = vec![interac_window, interac_window_camera, node_entity];
+    entities_list.extend(light_entities);
 
     // saving the entites to a list, so that we can easily despawn them when the window close
     operation_window.window = Some(interac_window);
@@ -193,6 +139,77 @@ pub fn interactive(
     operation_window.current_scene_entity = Some(scene_entity);
 }
 
+// get the loaded image back and process it so that it can be compatible for a 3d dimension
+pub fn prepare_skybox_image(image: &mut Image) {
+    if image.texture_descriptor.array_layer_count() == 1 {
+        image.reinterpret_stacked_2d_as_array((image.height() / image.width()).max(1));
+        image.texture_view_descriptor = Some(TextureViewDescriptor {
+            dimension: Some(TextureViewDimension::Cube),
+
+            ..Default::default()
+        });
+    }
+}
+
+// the index of the skybox of the pool for a live capture frame, the same seed and frame always give the same skybox
+pub fn pool_skybox_index(skybox_pool: &SkyboxPool, seed: u64, frame_index: usize) -> Option<usize> {
+    if skybox_pool.paths.is_empty() {
+        return None;
+    }
+
+    let mut rng = frame_rng(
+        seed,
+        SKYBOX_STREAM,
+        frame_index / skybox_pool.frames_per_skybox.max(1),
+    );
+    return Some((rng.next_u64() % skybox_pool.paths.len() as u64) as usize);
+}
+
+// switch the skybox when the current model has its own skybox in the capture job ( or back to the main menu's one ), the
+// camera keep the previous skybox until the new one is loaded, the live capture camera take over when there is a pool
+pub fn apply_model_skybox(
+    asset_server: Res<AssetServer>,
+    asset_path: Res<AssetPath>,
+    mut skybox_attributes: ResMut<SkyboxAttribute>,
+    mut images: ResMut<Assets<Image>>,
+    mut cameras: Query<&mut Skybox, With<OrbitCamera>>,
+    current_operation_state: Res<State<OperationState>>,
+    skybox_pool: Res<SkyboxPool>,
+) {
+    if *current_operation_state.get() == OperationState::LiveCapture
+        && !skybox_pool.paths.is_empty()
+    {
+        return;
+    }
+
+    let model_path = &asset_path.models_path[asset_path.current_model_path_count as usize];
+    let skybox_path = asset_path
+        .model_overrides
+        .get(model_path)
+        .and_then(|overrides| overrides.skybox_path.clone())
+        .unwrap_or(asset_path.skybox_path.clone());
+    if skybox_attributes.skybox_path != skybox_path {
+        skybox_attributes.skybox_handler = Some(asset_server.load(skybox_path.clone()));
+        skybox_attributes.skybox_path = skybox_path;
+    }
+
+    let skybox_handler = match skybox_attributes.skybox_handler.as_ref() {
+        Some(skybox_handler) => skybox_handler,
+        None => return,
+    };
+    if !asset_server.is_loaded(skybox_handler) {
+        return;
+    }
+    for mut skybox in cameras.iter_mut() {
+        if skybox.image != *skybox_handler {
+            if let Some(image) = images.get_mut(skybox_handler) {
+                prepare_skybox_image(image);
+            }
+            skybox.image = skybox_handler.clone();
+        }
+    }
+}
+
 // to reposition the model on the 3D environment
 pub fn reposition_rotate_model(
     mut query: Query<(&mut Transform, &mut ModelRotateReposition)>,
@@ -344,7 +361,7 @@ pub fn switch_model(
     }
     let model_path = assets_path.models_path[assets_path.current_model_path_count as usize].clone();
 
-    let scene_handler = asset_server.load(GltfAssetLabel::Scene(0).from_asset(model_path.clone()));
+    let scene_handler = load_model_scene(&asset_server, &model_path);
     let scene_entity = commands
         .spawn((
             SceneRoot(scene_handler.clone()),
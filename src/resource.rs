@@ -1,17 +1,33 @@
+use std::collections::{HashMap, VecDeque};
+
 use bevy::{
+    animation::graph::{AnimationGraph, AnimationNodeIndex},
     asset::Handle,
     ecs::{entity::Entity, system::Resource},
+    gltf::Gltf,
     image::Image,
     scene::Scene,
 };
 
-use crate::types::SavePath;
+use crate::{
+    channels::{CaptureDepthMaterial, CaptureMaskMaterial, CaptureNormalMaterial},
+    states::BatchCaptureStage,
+    types::{
+        AnimationCaptureSettings, CaptureManifest, CaptureProgress, DepthOutputFormat,
+        GltfSceneSelection, GroundPlaneSettings, InstanceLegendEntry, LightingRandomization,
+        LightingRig, ModelOverrides, NormalOutputSpace, SavePath, ViewpointSampler,
+    },
+};
 
 #[derive(Resource, Debug, Clone)]
 pub struct AssetPath {
     pub models_path: Vec<String>,
     pub skybox_path: String,
     pub current_model_path_count: i64,
+    // keyed by the model path, only the models loaded from a capture job have overrides
+    pub model_overrides: HashMap<String, ModelOverrides>,
+    // the scene rendered for the glTF models without an override
+    pub gltf_scene: GltfSceneSelection,
 }
 
 #[derive(Resource, Debug, Clone)]
@@ -32,6 +48,16 @@ pub struct OperationWindowRelatedEntities {
 #[derive(Resource)]
 pub struct SkyboxAttribute {
     pub skybox_handler: Option<Handle<Image>>,
+    // the skybox the handler was loaded from, a model can use another skybox than the one of the main menu
+    pub skybox_path: String,
+}
+
+// the skyboxes live capture pick from, the images are loaded on the first live capture that use them
+#[derive(Resource, Debug)]
+pub struct SkyboxPool {
+    pub paths: Vec<String>,
+    pub handlers: Vec<Handle<Image>>,
+    pub frames_per_skybox: usize,
 }
 
 #[derive(Resource, Debug)]
@@ -53,11 +79,106 @@ pub struct OperationSettings {
     pub model_reposition_sensitivity: f32,
     pub mouse_sensitivity: f32,
     pub zoom_sensitivity: f32,
+    pub ground_plane: Option<GroundPlaneSettings>,
 }
 
 #[derive(Resource, Debug)]
 pub struct LiveCaptureOperationSettings {
+    // the number of viewpoints to sample from the settings, live_capture_iteration is the one of the current run
+    pub configured_live_capture_iteration: usize,
     pub live_capture_iteration: usize,
     pub live_capture_iteration_current_counter: usize,
     pub live_capture_coordinate_list: Vec<(f32, f32, f32)>,
+    // the sampler and seed of the settings, live_capture_seed is the one of the current run ( the saved one when resumed )
+    pub viewpoint_sampler: ViewpointSampler,
+    pub seed: u64,
+    pub live_capture_seed: u64,
+    // a resumed run skip the viewpoints that already have a snapshot on disk
+    pub skip_existing: bool,
+    pub max_snapshots_in_flight: usize,
+    pub animation_capture: Option<AnimationCaptureSettings>,
+    pub lighting_randomization: Option<LightingRandomization>,
+    // the animation time of every coordinate of the run, empty when the run doesn't capture an animation
+    pub live_capture_animation_times: Vec<f32>,
+}
+
+// the lighting rig spawned with the next operation window
+#[derive(Resource, Debug)]
+pub struct LightingRigSettings {
+    pub rig: LightingRig,
+}
+
+// the ambient brightness to restore once a live capture with randomized lighting ends
+#[derive(Resource, Debug, Default)]
+pub struct DefaultLighting {
+    pub ambient_brightness: Option<f32>,
+}
+
+// the animation clips of the current model, the current clip is posed at `time` by the interactive scrubber or the live
+// capture instead of being played by bevy
+#[derive(Resource, Debug, Default)]
+pub struct ModelAnimation {
+    pub scene_entity: Option<Entity>,
+    pub gltf_handler: Option<Handle<Gltf>>,
+    // false until the clips of the model are known, a model that isn't a glTF file has no clip
+    pub loaded: bool,
+    pub clip_names: Vec<String>,
+    pub clip_durations: Vec<f32>,
+    pub graph: Option<Handle<AnimationGraph>>,
+    pub nodes: Vec<AnimationNodeIndex>,
+    pub current_clip: usize,
+    pub time: f32,
+    pub playing: bool,
+}
+
+// the snapshots still waiting for some of their screenshots, oldest first, as every screenshot of a snapshot is
+// rendered in the same frame they are also captured together and in order
+#[derive(Resource, Debug, Default)]
+pub struct SnapshotsInFlight {
+    pub pending_screenshots: VecDeque<PendingSnapshot>,
+    // the frame count of the current frame, so that a snapshot can be stamped with it when requested
+    pub current_frame: u32,
+}
+
+#[derive(Debug)]
+pub struct PendingSnapshot {
+    pub screenshots: usize,
+    pub requested_frame: u32,
+}
+
+// capture every model one after another without user interaction, either started from the command line ( and exit
+// MVC once done ) or with the "Capture All" button of the main menu
+#[derive(Resource, Debug)]
+pub struct BatchCapture {
+    pub stage: BatchCaptureStage,
+    pub exit_on_finish: bool,
+}
+
+// the manifest of the current capture run, will be written into the model's save directory once the run ends
+#[derive(Resource, Debug)]
+pub struct CaptureManifestRecord {
+    pub dir_path: String,
+    pub manifest: CaptureManifest,
+    pub progress: Option<CaptureProgress>,
+    pub pending_write: bool,
+}
+
+// the extra images captured alongside every RGB snapshot, the image handles are only set when the channel is enabled
+#[derive(Resource, Debug)]
+pub struct CaptureChannels {
+    pub depth_output: DepthOutputFormat,
+    pub depth_material: Option<Handle<CaptureDepthMaterial>>,
+    pub depth_image: Option<Handle<Image>>,
+    pub normal_output: NormalOutputSpace,
+    pub normal_material: Option<Handle<CaptureNormalMaterial>>,
+    pub normal_image: Option<Handle<Image>>,
+    pub mask_output: bool,
+    pub mask_material: Option<Handle<CaptureMaskMaterial>>,
+    pub mask_image: Option<Handle<Image>>,
+    pub rgba_output: bool,
+    pub rgba_image: Option<Handle<Image>>,
+    pub instance_output: bool,
+    pub instance_image: Option<Handle<Image>>,
+    pub instance_scene_entity: Option<Entity>,
+    pub instance_legend: Vec<InstanceLegendEntry>,
 }
@@ -0,0 +1,452 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use crate::types::ViewpointSampler;
+
+// the inverse of the golden ratio, used to spread the fibonacci lattice around the yaw axis
+const INVERSE_GOLDEN_RATIO: f32 = 0.618_034;
+
+// just short of the poles
+const MAX_PITCH: f32 = FRAC_PI_2 - 0.01;
+
+// the yaw / pitch / radius box the viewpoints are sampled from
+pub struct ViewpointRange {
+    pub yaw: (f32, f32),
+    pub pitch: (f32, f32),
+    pub radius: (f32, f32),
+}
+
+// generate the ( yaw, pitch, radius ) coordinates of a live capture run with the selected sampler, the grid and the
+// concentric rings sampler decide the number of viewpoints by themselves and are the same for every seed
+pub fn generate_viewpoints(
+    sampler: &ViewpointSampler,
+    count: usize,
+    seed: u64,
+    range: &ViewpointRange,
+) -> Vec<(f32, f32, f32)> {
+    let mut rng = SplitMix64::new(seed);
+    // the low discrepancy sequences are shifted by a random offset ( modulo 1 ) so that every seed give a different set
+    // of viewpoints with the same even coverage, seed 0 keep the unshifted sequences so that the default settings give
+    // the same viewpoints as before the seed existed
+    let offset = if seed == 0 {
+        (0.0, 0.0, 0.0)
+    } else {
+        (rng.next_f32(), rng.next_f32(), rng.next_f32())
+    };
+    let points = match sampler {
+        ViewpointSampler::Halton => halton_points(count, range, offset),
+        ViewpointSampler::FibonacciSphere => fibonacci_points(count, range, offset),
+        ViewpointSampler::UniformRandom => uniform_random_points(count, range, &mut rng),
+        ViewpointSampler::Grid {
+            yaw_steps,
+            pitch_steps,
+            radius_steps,
+        } => grid_points(*yaw_steps, *pitch_steps, *radius_steps, range),
+        ViewpointSampler::ConcentricRings { elevations } => {
+            concentric_ring_points(count, elevations, range)
+        }
+        ViewpointSampler::UpperHemisphere => fibonacci_points(
+            count,
+            &ViewpointRange {
+                yaw: (-PI, PI),
+                pitch: (-FRAC_PI_2, 0.0),
+                radius: range.radius,
+            },
+            offset,
+        ),
+    };
+
+    // the camera look at the model with +Y as up, which is degenerate from straight above or below
+    return points
+        .into_iter()
+        .map(|(yaw, pitch, radius)| (yaw, pitch.clamp(-MAX_PITCH, MAX_PITCH), radius))
+        .collect();
+}
+
+fn halton(index: u32, base: u32) -> f32 {
+    let mut result = 0.0;
+    let mut f = 1.0;
+    let mut i = index;
+
+    while i > 0 {
+        f /= base as f32;
+        result += f * (i % base) as f32;
+        i /= base;
+    }
+
+    return result;
+}
+
+fn lerp(range: (f32, f32), t: f32) -> f32 {
+    return range.0 + (range.1 - range.0) * t;
+}
+
+fn shift(t: f32, offset: f32) -> f32 {
+    return (t + offset).fract();
+}
+
+// Generate 3D points within specified ranges using the Halton sequence
+fn halton_points(
+    count: usize,
+    range: &ViewpointRange,
+    offset: (f32, f32, f32),
+) -> Vec<(f32, f32, f32)> {
+    return (0..count)
+        .map(|i| {
+            let x = lerp(range.yaw, shift(halton(i as u32 + 1, 2), offset.0));
+            let y = lerp(range.pitch, shift(halton(i as u32 + 1, 3), offset.1));
+            let z = lerp(range.radius, shift(halton(i as u32 + 1, 5), offset.2));
+            return (x, y, z);
+        })
+        .collect();
+}
+
+// the pitch of a point on the sphere, picked from the sine of the pitch so that the points are evenly spread by area
+// instead of bunching up toward the pole
+fn area_uniform_pitch(pitch_range: (f32, f32), t: f32) -> f32 {
+    return lerp((pitch_range.0.sin(), pitch_range.1.sin()), t).asin();
+}
+
+// fibonacci lattice over the part of the sphere inside the yaw / pitch range, the radius follow the Halton sequence
+fn fibonacci_points(
+    count: usize,
+    range: &ViewpointRange,
+    offset: (f32, f32, f32),
+) -> Vec<(f32, f32, f32)> {
+    return (0..count)
+        .map(|i| {
+            let yaw = lerp(range.yaw, shift(i as f32 * INVERSE_GOLDEN_RATIO, offset.0));
+            let pitch = area_uniform_pitch(range.pitch, (i as f32 + 0.5) / count as f32);
+            let radius = lerp(range.radius, shift(halton(i as u32 + 1, 2), offset.2));
+            return (yaw, pitch, radius);
+        })
+        .collect();
+}
+
+fn uniform_random_points(
+    count: usize,
+    range: &ViewpointRange,
+    rng: &mut SplitMix64,
+) -> Vec<(f32, f32, f32)> {
+    return (0..count)
+        .map(|_| {
+            let yaw = lerp(range.yaw, rng.next_f32());
+            let pitch = area_uniform_pitch(range.pitch, rng.next_f32());
+            let radius = lerp(range.radius, rng.next_f32());
+            return (yaw, pitch, radius);
+        })
+        .collect();
+}
+
+// evenly spaced steps including both ends of the range, a single step sit at the middle of the range
+fn steps(range: (f32, f32), count: usize) -> Vec<f32> {
+    if count <= 1 {
+        return vec![lerp(range, 0.5)];
+    }
+    return (0..count)
+        .map(|i| lerp(range, i as f32 / (count - 1) as f32))
+        .collect();
+}
+
+fn grid_points(
+    yaw_steps: usize,
+    pitch_steps: usize,
+    radius_steps: usize,
+    range: &ViewpointRange,
+) -> Vec<(f32, f32, f32)> {
+    let mut points = vec![];
+    for radius in steps(range.radius, radius_steps) {
+        for pitch in steps(range.pitch, pitch_steps) {
+            for yaw in steps(range.yaw, yaw_steps) {
+                points.push((yaw, pitch, radius));
+            }
+        }
+    }
+
+    return points;
+}
+
+// the viewpoints are split evenly between the rings, each ring going around the yaw range at a fixed elevation
+// ( radians above the horizon ) and at the radius where the model fit the frame
+fn concentric_ring_points(
+    count: usize,
+    elevations: &[f32],
+    range: &ViewpointRange,
+) -> Vec<(f32, f32, f32)> {
+    if elevations.is_empty() {
+        return vec![];
+    }
+
+    let points_per_ring = (count / elevations.len()).max(1);
+    let mut points = vec![];
+    for elevation in elevations {
+        for i in 0..points_per_ring {
+            let yaw = lerp(range.yaw, i as f32 / points_per_ring as f32);
+            // the camera is above the model with a negative pitch
+            points.push((yaw, -elevation, range.radius.0));
+        }
+    }
+
+    return points;
+}
+
+// the random generator of a single frame for one kind of random choice ( the stream ), so that a frame get the same
+// choices whatever the frames captured before it, a resumed run included
+pub fn frame_rng(seed: u64, stream: u64, frame_index: usize) -> SplitMix64 {
+    let stream_seed = SplitMix64::new(seed ^ stream).next_u64();
+    return SplitMix64::new(stream_seed ^ (frame_index as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+}
+
+// small seeded random generator, enough for sampling and doesn't need an extra dependency
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        return SplitMix64 { state: seed };
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        return z ^ (z >> 31);
+    }
+
+    // uniform in [0, 1)
+    pub fn next_f32(&mut self) -> f32 {
+        return (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RANGE: ViewpointRange = ViewpointRange {
+        yaw: (-1.0, 2.0),
+        pitch: (-1.2, 0.3),
+        radius: (2.0, 5.0),
+    };
+
+    // the samplers whose number of viewpoints is the requested count
+    fn counted_samplers() -> Vec<ViewpointSampler> {
+        return vec![
+            ViewpointSampler::Halton,
+            ViewpointSampler::FibonacciSphere,
+            ViewpointSampler::UniformRandom,
+            ViewpointSampler::UpperHemisphere,
+        ];
+    }
+
+    fn in_range(value: f32, range: (f32, f32)) -> bool {
+        // asin( sin( x ) ) can land a rounding error outside of the range
+        return value >= range.0 - 1e-5 && value <= range.1 + 1e-5;
+    }
+
+    #[test]
+    fn split_mix_64_matches_the_reference_sequence() {
+        let mut rng = SplitMix64::new(0);
+        assert_eq!(rng.next_u64(), 0xE220_A839_7B1D_CDAF);
+        assert_eq!(rng.next_u64(), 0x6E78_9E6A_A1B9_65F4);
+        assert_eq!(rng.next_u64(), 0x06C4_5D18_8009_454F);
+    }
+
+    #[test]
+    fn split_mix_64_floats_are_between_0_and_1() {
+        let mut rng = SplitMix64::new(42);
+        for _ in 0..10_000 {
+            let value = rng.next_f32();
+            assert!((0.0..1.0).contains(&value), "{}", value);
+        }
+    }
+
+    #[test]
+    fn frame_rng_depends_on_the_seed_stream_and_frame_only() {
+        assert_eq!(frame_rng(7, 1, 3).next_u64(), frame_rng(7, 1, 3).next_u64());
+        assert_ne!(frame_rng(7, 1, 3).next_u64(), frame_rng(8, 1, 3).next_u64());
+        assert_ne!(frame_rng(7, 1, 3).next_u64(), frame_rng(7, 2, 3).next_u64());
+        assert_ne!(frame_rng(7, 1, 3).next_u64(), frame_rng(7, 1, 4).next_u64());
+    }
+
+    #[test]
+    fn viewpoints_are_the_same_for_a_seed() {
+        let mut samplers = counted_samplers();
+        samplers.push(ViewpointSampler::Grid {
+            yaw_steps: 3,
+            pitch_steps: 2,
+            radius_steps: 2,
+        });
+        samplers.push(ViewpointSampler::ConcentricRings {
+            elevations: vec![0.2, 0.6],
+        });
+
+        for sampler in samplers {
+            assert_eq!(
+                generate_viewpoints(&sampler, 50, 1234, &RANGE),
+                generate_viewpoints(&sampler, 50, 1234, &RANGE),
+                "{:?}",
+                sampler
+            );
+        }
+    }
+
+    #[test]
+    fn random_samplers_change_with_the_seed() {
+        for sampler in [
+            ViewpointSampler::Halton,
+            ViewpointSampler::FibonacciSphere,
+            ViewpointSampler::UniformRandom,
+        ] {
+            assert_ne!(
+                generate_viewpoints(&sampler, 50, 1, &RANGE),
+                generate_viewpoints(&sampler, 50, 2, &RANGE),
+                "{:?}",
+                sampler
+            );
+        }
+    }
+
+    #[test]
+    fn viewpoints_stay_inside_the_range() {
+        for sampler in [
+            ViewpointSampler::Halton,
+            ViewpointSampler::FibonacciSphere,
+            ViewpointSampler::UniformRandom,
+            ViewpointSampler::Grid {
+                yaw_steps: 4,
+                pitch_steps: 3,
+                radius_steps: 2,
+            },
+        ] {
+            for seed in 0..20 {
+                let points = generate_viewpoints(&sampler, 200, seed, &RANGE);
+                assert!(!points.is_empty());
+                for (yaw, pitch, radius) in points {
+                    assert!(in_range(yaw, RANGE.yaw), "{:?} yaw {}", sampler, yaw);
+                    assert!(
+                        in_range(pitch, RANGE.pitch),
+                        "{:?} pitch {}",
+                        sampler,
+                        pitch
+                    );
+                    assert!(
+                        in_range(radius, RANGE.radius),
+                        "{:?} radius {}",
+                        sampler,
+                        radius
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn upper_hemisphere_keep_the_camera_above_the_model() {
+        for (yaw, pitch, radius) in
+            generate_viewpoints(&ViewpointSampler::UpperHemisphere, 200, 5, &RANGE)
+        {
+            assert!(in_range(yaw, (-PI, PI)), "yaw {}", yaw);
+            assert!(in_range(pitch, (-FRAC_PI_2, 0.0)), "pitch {}", pitch);
+            assert!(in_range(radius, RANGE.radius), "radius {}", radius);
+        }
+    }
+
+    #[test]
+    fn grid_and_rings_decide_their_own_count() {
+        let grid = ViewpointSampler::Grid {
+            yaw_steps: 4,
+            pitch_steps: 3,
+            radius_steps: 2,
+        };
+        assert_eq!(generate_viewpoints(&grid, 0, 0, &RANGE).len(), 24);
+        assert_eq!(generate_viewpoints(&grid, 1, 0, &RANGE).len(), 24);
+
+        let rings = ViewpointSampler::ConcentricRings {
+            elevations: vec![0.2, 0.6],
+        };
+        let points = generate_viewpoints(&rings, 10, 0, &RANGE);
+        assert_eq!(points.len(), 10);
+        assert!(points[..5].iter().all(|point| point.1 == -0.2));
+        assert!(points[5..].iter().all(|point| point.1 == -0.6));
+        assert!(points.iter().all(|point| point.2 == RANGE.radius.0));
+        // at least one viewpoint per ring
+        assert_eq!(generate_viewpoints(&rings, 0, 0, &RANGE).len(), 2);
+    }
+
+    #[test]
+    fn no_viewpoint_for_a_count_of_0() {
+        for sampler in counted_samplers() {
+            assert!(
+                generate_viewpoints(&sampler, 0, 9, &RANGE).is_empty(),
+                "{:?}",
+                sampler
+            );
+        }
+        let rings = ViewpointSampler::ConcentricRings { elevations: vec![] };
+        assert!(generate_viewpoints(&rings, 10, 9, &RANGE).is_empty());
+    }
+
+    #[test]
+    fn a_single_viewpoint_for_a_count_of_1() {
+        for sampler in counted_samplers() {
+            let points = generate_viewpoints(&sampler, 1, 9, &RANGE);
+            assert_eq!(points.len(), 1, "{:?}", sampler);
+            assert!(points[0].0.is_finite() && points[0].1.is_finite() && points[0].2.is_finite());
+        }
+
+        // a single step of the grid sit at the middle of the range
+        let grid = ViewpointSampler::Grid {
+            yaw_steps: 1,
+            pitch_steps: 1,
+            radius_steps: 1,
+        };
+        let points = generate_viewpoints(&grid, 1, 9, &RANGE);
+        assert_eq!(points.len(), 1);
+        assert!((points[0].0 - 0.5).abs() < 1e-5);
+        assert!((points[0].1 + 0.45).abs() < 1e-5);
+        assert!((points[0].2 - 3.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn seed_0_is_the_unshifted_halton_sequence() {
+        let points = generate_viewpoints(&ViewpointSampler::Halton, 4, 0, &RANGE);
+
+        // the baseline sampling, before the seed and the other samplers were added
+        let expected: Vec<(f32, f32, f32)> = (1..=4)
+            .map(|i| {
+                (
+                    lerp(RANGE.yaw, halton(i, 2)),
+                    lerp(RANGE.pitch, halton(i, 3)),
+                    lerp(RANGE.radius, halton(i, 5)),
+                )
+            })
+            .collect();
+        assert_eq!(points, expected);
+    }
+
+    #[test]
+    fn viewpoints_never_reach_the_poles() {
+        let range = ViewpointRange {
+            yaw: (-PI, PI),
+            pitch: (-FRAC_PI_2, FRAC_PI_2),
+            radius: (1.0, 1.0),
+        };
+        for sampler in [
+            ViewpointSampler::UpperHemisphere,
+            ViewpointSampler::Grid {
+                yaw_steps: 2,
+                pitch_steps: 3,
+                radius_steps: 1,
+            },
+            ViewpointSampler::ConcentricRings {
+                elevations: vec![FRAC_PI_2],
+            },
+        ] {
+            for (_, pitch, _) in generate_viewpoints(&sampler, 10_000, 3, &range) {
+                assert!(pitch.abs() <= MAX_PITCH, "{:?} pitch {}", sampler, pitch);
+            }
+        }
+    }
+}
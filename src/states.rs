@@ -30,3 +30,13 @@ pub enum RenderModelForwardOrBackward {
     Forward,
     Backward,
 }
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Default)]
+pub enum BatchCaptureStage {
+    #[default]
+    OpenOperationWindow,
+    StartCapture,
+    Capturing,
+    Finished,
+    Cancelled,
+}
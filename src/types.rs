@@ -1,3 +1,8 @@
+use std::{
+    collections::HashMap,
+    f32::consts::{FRAC_PI_2, FRAC_PI_4},
+};
+
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -13,10 +18,531 @@ pub struct AppSettings {
     pub mouse_sensitivity: f32,
     pub zoom_sensitivity: f32,
     pub live_capture_iteration: usize,
+    #[serde(default)]
+    pub depth_output: DepthOutputFormat,
+    #[serde(default)]
+    pub normal_output: NormalOutputSpace,
+    #[serde(default)]
+    pub mask_output: bool,
+    #[serde(default)]
+    pub rgba_output: bool,
+    #[serde(default)]
+    pub instance_output: bool,
+    #[serde(default)]
+    pub viewpoint_sampler: ViewpointSampler,
+    // drive every random choice of a capture run, the same seed and settings regenerate the same dataset
+    #[serde(default)]
+    pub seed: u64,
+    // how many snapshots can wait for their screenshots before the live capture camera stop moving
+    #[serde(default = "default_max_snapshots_in_flight")]
+    pub max_snapshots_in_flight: usize,
+    // the scene rendered for the glTF models dropped in the main menu, a capture job can pick another one per model
+    #[serde(default)]
+    pub gltf_scene: GltfSceneSelection,
+    #[serde(default)]
+    pub animation_capture: Option<AnimationCaptureSettings>,
+    #[serde(default)]
+    pub lighting_randomization: Option<LightingRandomization>,
+    // the lights of the operation window, a rig file ( when given ) take the place of the rig written here
+    #[serde(default)]
+    pub lighting_rig: LightingRig,
+    #[serde(default)]
+    pub lighting_rig_path: Option<String>,
+    #[serde(default)]
+    pub ground_plane: Option<GroundPlaneSettings>,
+    // live capture pick the skybox of every frame from this pool instead of using the one of the main menu
+    #[serde(default)]
+    pub skybox_pool: Option<SkyboxPoolSettings>,
+}
+
+fn default_max_snapshots_in_flight() -> usize {
+    return 1;
 }
 
 #[derive(Debug, Clone)]
 pub struct SavePath {
     pub current_dir_path: String,
     pub file_name_prefix: String,
+    // the output directory the snapshot directory is placed under ( the settings' one or the capture job's one )
+    pub output_dir_path: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct BatchCaptureArgs {
+    pub models_path: Vec<String>,
+    pub skybox_path: String,
+    pub output_dir: String,
+    pub model_overrides: HashMap<String, ModelOverrides>,
+}
+
+// a capture job listing the models to capture, relative paths are relative to the job file and anything that isn't set
+// fall back to the command line arguments or the settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureJob {
+    pub output_dir: Option<String>,
+    pub skybox_path: Option<String>,
+    pub models: Vec<CaptureJobModel>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureJobModel {
+    pub path: String,
+    #[serde(flatten)]
+    pub overrides: ModelOverrides,
+}
+
+// the per model settings of a capture job, replacing the settings for this model only
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModelOverrides {
+    pub skybox_path: Option<String>,
+    pub yaw_min_value: Option<f32>,
+    pub yaw_max_value: Option<f32>,
+    pub pitch_min_value: Option<f32>,
+    pub pitch_max_value: Option<f32>,
+    pub radius_range: Option<f32>,
+    pub live_capture_iteration: Option<usize>,
+    pub gltf_scene: Option<GltfSceneSelection>,
+}
+
+// which scene of a glTF file is rendered, a file with several scenes ( variants, LODs ) can also be captured as one model
+// per scene, each with its own save directory
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GltfSceneSelection {
+    Index { index: usize },
+    Name { name: String },
+    All,
+}
+
+impl Default for GltfSceneSelection {
+    fn default() -> Self {
+        return GltfSceneSelection::Index { index: 0 };
+    }
+}
+
+pub const DEFAULT_ANIMATION_FRAME_RATE: f32 = 10.0;
+
+// capture an animation clip of the model across time as well as viewpoint, every viewpoint ( or only the camera's
+// current one when fixed_viewpoint is set ) capture the clip from its start to its end at the frame rate
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnimationCaptureSettings {
+    pub clip_index: usize,
+    #[serde(default = "default_animation_frame_rate")]
+    pub frame_rate: f32,
+    #[serde(default)]
+    pub fixed_viewpoint: bool,
+}
+
+fn default_animation_frame_rate() -> f32 {
+    return DEFAULT_ANIMATION_FRAME_RATE;
+}
+
+// randomize the lighting of every live capture frame within these [min, max] ranges ( domain randomization ), the
+// fixed lights are replaced by directional lights coming from above the horizon at a random yaw
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LightingRandomization {
+    pub light_count: (usize, usize),
+    // lux
+    pub illuminance: (f32, f32),
+    // kelvin
+    pub color_temperature: (f32, f32),
+    // radians above the horizon, kept just short of the zenith
+    pub elevation: (f32, f32),
+    pub ambient_brightness: (f32, f32),
+}
+
+impl Default for LightingRandomization {
+    fn default() -> Self {
+        return LightingRandomization {
+            light_count: (1, 4),
+            illuminance: (300.0, 2000.0),
+            color_temperature: (2700.0, 7500.0),
+            elevation: (0.2, 1.4),
+            ambient_brightness: (100.0, 800.0),
+        };
+    }
+}
+
+// the lights spawned with the operation window and the intensity of the ambient and environment map lights, the
+// default rig is the studio lighting of the operation window ( 4 directional lights )
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LightingRig {
+    pub lights: Vec<RigLight>,
+    pub ambient_brightness: f32,
+    pub environment_map_intensity: f32,
+}
+
+impl Default for LightingRig {
+    fn default() -> Self {
+        let directional_light = |rotation: [f32; 3]| RigLight {
+            kind: RigLightKind::Directional { illuminance: 800.0 },
+            translation: [0.0, 0.0, 0.0],
+            rotation,
+            color: default_light_color(),
+            shadows_enabled: false,
+        };
+        return LightingRig {
+            lights: vec![
+                // light from top
+                directional_light([0.0, 0.0, 0.0]),
+                // light from front
+                directional_light([-FRAC_PI_2, 0.0, 0.0]),
+                // light from top right
+                directional_light([-FRAC_PI_4, FRAC_PI_4, 0.0]),
+                // light from top left
+                directional_light([-FRAC_PI_4, -FRAC_PI_4, 0.0]),
+            ],
+            ambient_brightness: 800.0,
+            environment_map_intensity: 250.0,
+        };
+    }
+}
+
+// a light of the rig, the rotation is the XYZ euler angles in radians ( a light point toward -Z without rotation ) and
+// the color is in sRGB
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RigLight {
+    #[serde(flatten)]
+    pub kind: RigLightKind,
+    #[serde(default)]
+    pub translation: [f32; 3],
+    #[serde(default)]
+    pub rotation: [f32; 3],
+    #[serde(default = "default_light_color")]
+    pub color: [f32; 3],
+    #[serde(default)]
+    pub shadows_enabled: bool,
+}
+
+// the illuminance is in lux, the intensity in lumens and the angles of the spot light in radians
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RigLightKind {
+    Directional {
+        illuminance: f32,
+    },
+    Point {
+        intensity: f32,
+        #[serde(default = "default_light_range")]
+        range: f32,
+    },
+    Spot {
+        intensity: f32,
+        #[serde(default = "default_light_range")]
+        range: f32,
+        inner_angle: f32,
+        outer_angle: f32,
+    },
+}
+
+fn default_light_color() -> [f32; 3] {
+    return [1.0, 1.0, 1.0];
+}
+
+fn default_light_range() -> f32 {
+    return 20.0;
+}
+
+// a ground plane under the model so that the renders show its contact shadows, every directional light cast shadows
+// while it is enabled
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GroundPlaneSettings {
+    pub mode: GroundPlaneMode,
+    // sRGB, only for the visible ground plane
+    pub color: [f32; 3],
+    // how dark the shadows of the shadow only ground plane are, from 0 to 1
+    pub shadow_opacity: f32,
+    // the side of the plane as a multiple of the camera distance to the model
+    pub size: f32,
+}
+
+impl Default for GroundPlaneSettings {
+    fn default() -> Self {
+        return GroundPlaneSettings {
+            mode: GroundPlaneMode::Visible,
+            color: [0.5, 0.5, 0.5],
+            shadow_opacity: 0.6,
+            size: 10.0,
+        };
+    }
+}
+
+// the shadow only ground plane is invisible except for the shadows it catch, so the skybox is seen through it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroundPlaneMode {
+    #[default]
+    Visible,
+    ShadowOnly,
+}
+
+// the skyboxes of the pool are image files or folders of images, the same skybox is kept for frames_per_skybox frames
+// in a row
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkyboxPoolSettings {
+    pub paths: Vec<String>,
+    #[serde(default = "default_frames_per_skybox")]
+    pub frames_per_skybox: usize,
+}
+
+fn default_frames_per_skybox() -> usize {
+    return 1;
+}
+
+// the lighting a snapshot was taken with when the lighting is randomized
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LightingRecord {
+    pub ambient_brightness: f32,
+    pub lights: Vec<LightRecord>,
+}
+
+// the direction is the one the light travel toward in world space, the color is the linear RGB of the color temperature
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LightRecord {
+    pub direction: [f32; 3],
+    pub illuminance: f32,
+    pub color_temperature: f32,
+    pub color: [f32; 3],
+}
+
+// the animation pose of a snapshot, the time is in seconds from the start of the clip
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnimationFrameRecord {
+    pub clip_index: usize,
+    pub clip_name: String,
+    pub time: f32,
+}
+
+// the camera parameters of a single snapshot, the focal length and principal point are in pixels
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CameraIntrinsics {
+    pub width: u32,
+    pub height: u32,
+    pub fov_y: f32,
+    pub aspect_ratio: f32,
+    pub near: f32,
+    pub far: f32,
+    pub fx: f32,
+    pub fy: f32,
+    pub cx: f32,
+    pub cy: f32,
+}
+
+// the camera to world matrix is row-major and follow bevy's convention ( right handed, +Y up, camera looking toward -Z )
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrameRecord {
+    pub file_name: String,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub radius: f32,
+    pub camera_to_world: [[f32; 4]; 4],
+    pub intrinsics: CameraIntrinsics,
+    // [x, y, width, height] in pixels of the model's projected bounding box
+    pub bbox: Option<[f32; 4]>,
+    pub pose: Option<PoseRecord>,
+    pub depth: Option<DepthRecord>,
+    pub normal: Option<NormalRecord>,
+    pub mask_file_name: Option<String>,
+    pub rgba_file_name: Option<String>,
+    pub instance_file_name: Option<String>,
+    pub animation: Option<AnimationFrameRecord>,
+    pub lighting: Option<LightingRecord>,
+    // the path of the skybox picked from the pool
+    pub skybox: Option<String>,
+}
+
+// the model to camera pose follow the OpenCV convention ( +Y down, camera looking toward +Z ) as pose estimation tooling
+// expect, the model to world matrix is row-major and any scale of the model is baked into the bounding box corners
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoseRecord {
+    pub model_to_world: [[f32; 4]; 4],
+    pub model_to_camera_rotation: [[f32; 3]; 3],
+    pub model_to_camera_translation: [f32; 3],
+    // the 8 corners of the model's oriented bounding box in model space
+    pub bbox_3d: [[f32; 3]; 8],
+    // the same corners projected in pixels, None when the corner is behind the camera
+    pub projected_bbox_3d: [Option<[f32; 2]>; 8],
+}
+
+// the depth image is linear depth, for png16 0 is the background and the [min_depth, max_depth] range ( the bounding box
+// of the model seen from the camera ) is mapped to [1, 65535], the exr file store the 32 bit float depth and 0 for the
+// background
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DepthRecord {
+    pub file_name: String,
+    pub format: DepthOutputFormat,
+    pub near: f32,
+    pub far: f32,
+    #[serde(default)]
+    pub min_depth: f32,
+    #[serde(default)]
+    pub max_depth: f32,
+    // the largest step between two depth values the file can hold ( at max_depth ), in world units
+    #[serde(default)]
+    pub precision: f32,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DepthOutputFormat {
+    #[default]
+    None,
+    Png16,
+    Exr,
+}
+
+// how the live capture viewpoints are picked, every sampler except the upper hemisphere stay inside the yaw / pitch /
+// radius range of the settings
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ViewpointSampler {
+    #[default]
+    Halton,
+    FibonacciSphere,
+    UniformRandom,
+    Grid {
+        yaw_steps: usize,
+        pitch_steps: usize,
+        radius_steps: usize,
+    },
+    // elevations are in radians above the horizon
+    ConcentricRings {
+        elevations: Vec<f32>,
+    },
+    UpperHemisphere,
+}
+
+// the state of a live capture run, kept in the model's directory so that an interrupted run can be resumed with the
+// exact same viewpoints
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureProgress {
+    pub seed: u64,
+    pub viewpoint_sampler: ViewpointSampler,
+    pub coordinate_list: Vec<(f32, f32, f32)>,
+    pub current_counter: usize,
+    // the animation time of every coordinate, empty when the run doesn't capture an animation
+    #[serde(default)]
+    pub animation_clip: Option<usize>,
+    #[serde(default)]
+    pub animation_times: Vec<f32>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CaptureManifest {
+    pub model_name: String,
+    // what is needed on top of the settings file to regenerate the same viewpoints
+    #[serde(default)]
+    pub seed: u64,
+    #[serde(default)]
+    pub viewpoint_sampler: ViewpointSampler,
+    pub frames: Vec<FrameRecord>,
+    #[serde(default)]
+    pub instances: Vec<InstanceLegendEntry>,
+}
+
+// every mesh of the model get an instance id, the node name and mesh name are the ones from the glTF file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstanceLegendEntry {
+    pub id: u32,
+    pub node_name: String,
+    pub mesh_name: String,
+}
+
+// the `transforms.json` layout expected by NeRF / Instant-NGP tooling, the transform matrix is camera to world in the
+// OpenGL convention which is the same as bevy's
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NerfTransforms {
+    pub camera_angle_x: f32,
+    pub camera_angle_y: f32,
+    pub fl_x: f32,
+    pub fl_y: f32,
+    pub cx: f32,
+    pub cy: f32,
+    pub w: u32,
+    pub h: u32,
+    pub frames: Vec<NerfFrame>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NerfFrame {
+    pub file_path: String,
+    pub transform_matrix: [[f32; 4]; 4],
+}
+
+// the normal image store the unit normal remapped from [-1, 1] to [0, 255], the background is black
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NormalRecord {
+    pub file_name: String,
+    pub space: NormalOutputSpace,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NormalOutputSpace {
+    #[default]
+    None,
+    World,
+    Camera,
+}
+
+// the COCO object detection layout, every snapshot have a single annotation for the model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CocoDataset {
+    pub images: Vec<CocoImage>,
+    pub annotations: Vec<CocoAnnotation>,
+    pub categories: Vec<CocoCategory>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CocoImage {
+    pub id: usize,
+    pub file_name: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CocoAnnotation {
+    pub id: usize,
+    pub image_id: usize,
+    pub category_id: usize,
+    pub bbox: [f32; 4],
+    pub area: f32,
+    pub iscrowd: u8,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CocoCategory {
+    pub id: usize,
+    pub name: String,
+}
+
+// BOP's scene_camera.json entry, the rotation matrix is row-major and the translation is in millimeters
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BopSceneCamera {
+    #[serde(rename = "cam_K")]
+    pub cam_k: [f32; 9],
+    #[serde(rename = "cam_R_w2c")]
+    pub cam_r_w2c: [f32; 9],
+    pub cam_t_w2c: [f32; 3],
+}
+
+// BOP's scene_gt.json entry, the model's scale is left out so the rotation stay a pure rotation, the points of the model
+// file have to be scaled by the scale of the model's transform before being posed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BopGroundTruth {
+    #[serde(rename = "cam_R_m2c")]
+    pub cam_r_m2c: [f32; 9],
+    pub cam_t_m2c: [f32; 3],
+    pub obj_id: u32,
+}
+
+// the projected bounding box corners of an object, kept out of scene_gt.json as it isn't part of BOP's format
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BopProjectedBox {
+    pub obj_id: u32,
+    pub projected_bbox_3d: [Option<[f32; 2]>; 8],
 }
@@ -1,28 +1,43 @@
 use std::{
-    fs::{create_dir_all, File, OpenOptions},
+    collections::HashMap,
+    fs::{create_dir_all, read_dir, File, OpenOptions},
     path::{Path, PathBuf},
 };
 
 use bevy::{
     prelude::*,
+    render::primitives::Aabb,
     window::{PrimaryWindow, WindowCloseRequested},
 };
 
 use crate::{
-    capture::take_snapshot,
-    components::OrbitCamera,
+    animation::animation_frame_record,
+    capture::{model_bounds, take_snapshot},
+    components::{CaptureChannelMesh, GroundPlane, ModelRotateReposition, OrbitCamera},
+    export::{read_capture_manifest, read_capture_progress, write_capture_progress},
     resource::{
-        LiveCameraPanNumber, LiveCaptureOperationSettings, OperationSettings,
-        OperationWindowRelatedEntities, SavePathList,
+        AssetPath, CaptureChannels, CaptureManifestRecord, LiveCameraPanNumber,
+        LiveCaptureOperationSettings, ModelAnimation, OperationSettings,
+        OperationWindowRelatedEntities, SavePathList, SkyboxPool, SnapshotsInFlight,
     },
+    sampler::{generate_viewpoints, ViewpointRange},
     states::{AppState, CameraFovInitializedState, OperationState},
-    types::AppSettings,
+    types::{
+        AppSettings, BatchCaptureArgs, CaptureJob, CaptureManifest, CaptureProgress,
+        DepthOutputFormat, FrameRecord, GltfSceneSelection, LightingRig, ModelOverrides,
+        NormalOutputSpace, SavePath, SkyboxPoolSettings, ViewpointSampler,
+        DEFAULT_ANIMATION_FRAME_RATE,
+    },
 };
 
+const BATCH_USAGE: &str =
+    "usage: mvc --skybox <skybox file> --output <output directory> <model file> [<model file> ...]\n       \
+     mvc --job <capture job file> [--skybox <skybox file>] [--output <output directory>] [<model file> ...]";
+
 pub fn check_model_file(file_path: &str) -> bool {
     match Path::new(file_path).extension() {
         Some(ext) => {
-            if ext == "glb" || ext == "gltf" {
+            if ext == "glb" || ext == "gltf" || ext == "obj" || ext == "stl" || ext == "ply" {
                 return true;
             } else {
                 return false;
@@ -32,6 +47,88 @@ pub fn check_model_file(file_path: &str) -> bool {
     }
 }
 
+// every model file ( glb / gltf / obj / stl / ply ) inside the directory and its sub directories, sorted so that the import order is stable
+pub fn find_model_files(dir_path: &Path) -> Vec<PathBuf> {
+    let mut model_files = vec![];
+    let entries = match read_dir(dir_path) {
+        Ok(entries) => entries,
+        Err(err) => {
+            eprintln!("❌ Failed to read directory {:?}: {}", dir_path, err);
+            return model_files;
+        }
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            model_files.extend(find_model_files(&path));
+        } else if check_model_file(&path.to_string_lossy()) {
+            model_files.push(path);
+        }
+    }
+    model_files.sort();
+
+    return model_files;
+}
+
+pub fn find_skybox_files(dir_path: &Path) -> Vec<PathBuf> {
+    let mut skybox_files = vec![];
+    let entries = match read_dir(dir_path) {
+        Ok(entries) => entries,
+        Err(err) => {
+            eprintln!("❌ Failed to read directory {:?}: {}", dir_path, err);
+            return skybox_files;
+        }
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            skybox_files.extend(find_skybox_files(&path));
+        } else if check_skybox_file(&path.to_string_lossy()) {
+            skybox_files.push(path);
+        }
+    }
+    skybox_files.sort();
+
+    return skybox_files;
+}
+
+// the skybox files of the pool, a folder bring every skybox inside it
+pub fn skybox_pool_paths(skybox_pool: &SkyboxPoolSettings) -> Vec<String> {
+    let mut skybox_paths = vec![];
+    for path in skybox_pool.paths.iter() {
+        let path_buf = Path::new(path);
+        if path_buf.is_dir() {
+            skybox_paths.extend(
+                find_skybox_files(path_buf)
+                    .iter()
+                    .map(|skybox_path| skybox_path.to_string_lossy().to_string()),
+            );
+        } else if check_skybox_file(path) && path_buf.is_file() {
+            skybox_paths.push(path.clone());
+        } else {
+            eprintln!("❌ invalid skybox file {}", path);
+        }
+    }
+
+    return skybox_paths;
+}
+
+// the skybox pool of the settings, empty when live capture keep the skybox of the main menu
+pub fn settings_skybox_pool(app_settings: &AppSettings) -> SkyboxPool {
+    return match app_settings.skybox_pool.as_ref() {
+        Some(skybox_pool) => SkyboxPool {
+            paths: skybox_pool_paths(skybox_pool),
+            handlers: vec![],
+            frames_per_skybox: skybox_pool.frames_per_skybox.max(1),
+        },
+        None => SkyboxPool {
+            paths: vec![],
+            handlers: vec![],
+            frames_per_skybox: 1,
+        },
+    };
+}
+
 pub fn check_skybox_file(file_path: &str) -> bool {
     match Path::new(file_path).extension() {
         Some(ext) => {
@@ -75,6 +172,8 @@ pub fn switch_state_on_window_event(
     mut camera_init_state: ResMut<NextState<CameraFovInitializedState>>,
     mut operation_window: ResMut<OperationWindowRelatedEntities>,
     mut live_camera_pan_number: ResMut<LiveCameraPanNumber>,
+    mut snapshots_in_flight: ResMut<SnapshotsInFlight>,
+    ground_planes: Query<Entity, With<GroundPlane>>,
 ) {
     for ev in window_close_requested_events.read() {
         if let Some(op_window) = operation_window.window {
@@ -85,9 +184,14 @@ pub fn switch_state_on_window_event(
                 live_camera_pan_number.yaw = 1.0;
                 live_camera_pan_number.pitch = 1.0;
                 live_camera_pan_number.radius = 1.0;
+                // the screenshots of a closed window will never be captured
+                snapshots_in_flight.pending_screenshots.clear();
                 for entity in operation_window.entities_list.as_mut().unwrap() {
                     commands.entity(*entity).despawn_recursive();
                 }
+                for ground_plane in ground_planes.iter() {
+                    commands.entity(ground_plane).despawn();
+                }
                 commands
                     .entity(operation_window.current_scene_entity.unwrap())
                     .despawn();
@@ -96,7 +200,7 @@ pub fn switch_state_on_window_event(
     }
 }
 
-// check keyboard in interactive mode for space bar to take snapshot
+// check keyboard in interactive mode for space bar to take snapshot, R resume the interrupted live capture of the model
 pub fn keyboard_interact(
     keys: Res<ButtonInput<KeyCode>>,
     mut operation_state: ResMut<NextState<OperationState>>,
@@ -105,14 +209,27 @@ pub fn keyboard_interact(
     // to pass to take_snapshot function
     commands: Commands,
     operation_window: Res<OperationWindowRelatedEntities>,
-    query: Query<&OrbitCamera>,
+    query: Query<(&OrbitCamera, &Transform, &Projection)>,
     mut operation_settings: ResMut<OperationSettings>,
     mut live_capture_settings: ResMut<LiveCaptureOperationSettings>,
     save_settings: Res<SavePathList>,
+    mut capture_manifest: ResMut<CaptureManifestRecord>,
+    capture_channels: Res<CaptureChannels>,
     mut window_query: Query<&mut Window, Without<PrimaryWindow>>,
+    meshes: Query<
+        (&GlobalTransform, &Aabb),
+        (
+            With<Mesh3d>,
+            Without<CaptureChannelMesh>,
+            Without<GroundPlane>,
+        ),
+    >,
+    models: Query<&GlobalTransform, With<ModelRotateReposition>>,
+    mut snapshots_in_flight: ResMut<SnapshotsInFlight>,
+    (asset_path, mut model_animation): (Res<AssetPath>, ResMut<ModelAnimation>),
 ) {
     let c_o_s = current_operation_state.as_ref().get();
-    let orbit_camera = query.get_single().unwrap();
+    let (orbit_camera, camera_transform, projection) = query.get_single().unwrap();
 
     if *c_o_s == OperationState::LiveCapture {
         if keys.just_pressed(KeyCode::Space) {
@@ -146,39 +263,30 @@ pub fn keyboard_interact(
         operation_settings.radius_start_position = orbit_camera.radius;
         if keys.just_pressed(KeyCode::Space) {
             println!("start live capturing 🎥");
-
-            // generate the evenly distributed coordinates
-            let coordinates_list = generate_points(
-                live_capture_settings.live_capture_iteration,
-                (
-                    operation_settings.yaw_min_value,
-                    operation_settings.yaw_max_value,
-                ),
-                (
-                    operation_settings.pitch_min_value,
-                    operation_settings.pitch_max_value,
-                ),
-                (
-                    operation_settings.radius_start_position,
-                    operation_settings.radius_start_position + operation_settings.radius_range,
-                ),
+            start_live_capture(
+                &operation_settings,
+                &mut live_capture_settings,
+                &save_settings,
+                &mut capture_manifest,
+                &mut window_query,
+                asset_path
+                    .model_overrides
+                    .get(&asset_path.models_path[asset_path.current_model_path_count as usize]),
+                &mut model_animation,
+                (orbit_camera.yaw, orbit_camera.pitch, orbit_camera.radius),
             );
-
-            // set the live capture settings
-            live_capture_settings.live_capture_iteration = coordinates_list.len();
-            live_capture_settings.live_capture_coordinate_list = coordinates_list;
-            live_capture_settings.live_capture_iteration_current_counter = 0;
-
-            // init the directory to save the snapshot
-            snapshot_directory_init(save_settings.clone());
-            for mut window in window_query.iter_mut() {
-                window.title = format!(
-                    "Live Capturing 🎥 [{}/{}]",
-                    live_capture_settings.live_capture_iteration_current_counter,
-                    live_capture_settings.live_capture_iteration
-                );
-            }
             operation_state.set(OperationState::LiveCapture);
+        } else if keys.just_pressed(KeyCode::KeyR) {
+            if resume_live_capture(
+                &mut live_capture_settings,
+                &save_settings,
+                &mut capture_manifest,
+                &mut window_query,
+                &mut model_animation,
+            ) {
+                println!("resume live capturing 🎥");
+                operation_state.set(OperationState::LiveCapture);
+            }
         } else if keys.just_pressed(KeyCode::KeyL) {
             println!("start live prviewing 🎬");
             for mut window in window_query.iter_mut() {
@@ -191,17 +299,625 @@ pub fn keyboard_interact(
     // no matter the operation state, when key c is press capture 1 copy of current model snapshot
     if keys.just_pressed(KeyCode::KeyC) {
         snapshot_directory_init(save_settings.clone());
+        let window = window_query
+            .get(operation_window.window.unwrap())
+            .expect("window not found");
         take_snapshot(
             commands,
             operation_window,
             &save_settings,
-            orbit_camera.yaw,
-            orbit_camera.pitch,
-            orbit_camera.radius,
+            &mut capture_manifest,
+            &capture_channels,
+            orbit_camera,
+            camera_transform,
+            projection,
+            window,
+            &model_bounds(models.get_single().ok(), meshes.iter()),
+            &mut snapshots_in_flight,
+            animation_frame_record(&model_animation),
+            None,
+            None,
+        );
+    }
+}
+
+// generate the coordinates for a live capture run and reset the counter, the caller is responsible to switch to the live capture state
+pub fn start_live_capture(
+    operation_settings: &OperationSettings,
+    live_capture_settings: &mut LiveCaptureOperationSettings,
+    save_settings: &SavePathList,
+    capture_manifest: &mut CaptureManifestRecord,
+    window_query: &mut Query<&mut Window, Without<PrimaryWindow>>,
+    model_overrides: Option<&ModelOverrides>,
+    model_animation: &mut ModelAnimation,
+    current_viewpoint: (f32, f32, f32),
+) {
+    // generate the coordinates with the sampler chosen in the settings, the capture job can override the ranges and the
+    // number of viewpoints of the model
+    let overrides = model_overrides.cloned().unwrap_or_default();
+    let mut coordinates_list = generate_viewpoints(
+        &live_capture_settings.viewpoint_sampler,
+        overrides
+            .live_capture_iteration
+            .unwrap_or(live_capture_settings.configured_live_capture_iteration),
+        live_capture_settings.seed,
+        &ViewpointRange {
+            yaw: (
+                overrides
+                    .yaw_min_value
+                    .unwrap_or(operation_settings.yaw_min_value),
+                overrides
+                    .yaw_max_value
+                    .unwrap_or(operation_settings.yaw_max_value),
+            ),
+            pitch: (
+                overrides
+                    .pitch_min_value
+                    .unwrap_or(operation_settings.pitch_min_value),
+                overrides
+                    .pitch_max_value
+                    .unwrap_or(operation_settings.pitch_max_value),
+            ),
+            radius: (
+                operation_settings.radius_start_position,
+                operation_settings.radius_start_position
+                    + overrides
+                        .radius_range
+                        .unwrap_or(operation_settings.radius_range),
+            ),
+        },
+    );
+
+    // an animation capture go through every frame of the clip at each viewpoint
+    let mut animation_clip = None;
+    let mut animation_times = vec![];
+    if let Some(animation_capture) = live_capture_settings.animation_capture.clone() {
+        match model_animation
+            .clip_durations
+            .get(animation_capture.clip_index)
+        {
+            Some(duration) => {
+                let frame_rate = if animation_capture.frame_rate > 0.0 {
+                    animation_capture.frame_rate
+                } else {
+                    DEFAULT_ANIMATION_FRAME_RATE
+                };
+                let frame_times: Vec<f32> = (0..=(duration * frame_rate).floor() as usize)
+                    .map(|frame| frame as f32 / frame_rate)
+                    .collect();
+                if animation_capture.fixed_viewpoint {
+                    coordinates_list = vec![current_viewpoint];
+                }
+                for _ in 0..coordinates_list.len() {
+                    animation_times.extend(frame_times.iter());
+                }
+                coordinates_list = coordinates_list
+                    .into_iter()
+                    .flat_map(|coordinates| std::iter::repeat(coordinates).take(frame_times.len()))
+                    .collect();
+                model_animation.current_clip = animation_capture.clip_index;
+                animation_clip = Some(animation_capture.clip_index);
+            }
+            None => {
+                println!(
+                    "⚠️ The model has no animation clip {}, capturing without animation",
+                    animation_capture.clip_index
+                );
+            }
+        }
+    }
+
+    // set the live capture settings
+    live_capture_settings.live_capture_iteration = coordinates_list.len();
+    live_capture_settings.live_capture_coordinate_list = coordinates_list;
+    live_capture_settings.live_capture_animation_times = animation_times;
+    live_capture_settings.live_capture_iteration_current_counter = 0;
+    live_capture_settings.live_capture_seed = live_capture_settings.seed;
+    live_capture_settings.skip_existing = false;
+
+    // the run add its frames to the manifest of the directory, the seed and sampler are the ones of the latest run
+    capture_manifest.dir_path = save_settings.save_path_list
+        [save_settings.current_path_count as usize]
+        .current_dir_path
+        .clone();
+    capture_manifest.manifest =
+        read_capture_manifest(Path::new(&capture_manifest.dir_path)).unwrap_or_default();
+    capture_manifest.manifest.seed = live_capture_settings.seed;
+    capture_manifest.manifest.viewpoint_sampler = live_capture_settings.viewpoint_sampler.clone();
+    capture_manifest.progress = Some(CaptureProgress {
+        seed: live_capture_settings.seed,
+        viewpoint_sampler: live_capture_settings.viewpoint_sampler.clone(),
+        coordinate_list: live_capture_settings.live_capture_coordinate_list.clone(),
+        current_counter: 0,
+        animation_clip,
+        animation_times: live_capture_settings.live_capture_animation_times.clone(),
+    });
+
+    // init the directory to save the snapshot, the progress is saved right away so that the run can be resumed even if
+    // MVC didn't exit properly
+    snapshot_directory_init(save_settings.clone());
+    write_capture_progress(
+        Path::new(&capture_manifest.dir_path),
+        capture_manifest.progress.as_ref().unwrap(),
+    );
+    for mut window in window_query.iter_mut() {
+        window.title = format!(
+            "Live Capturing 🎥 [{}/{}]",
+            live_capture_settings.live_capture_iteration_current_counter,
+            live_capture_settings.live_capture_iteration
         );
     }
 }
 
+// continue the interrupted live capture of the current model with the saved coordinates, the viewpoints that already
+// have a snapshot on disk are skipped, return false if there is nothing to resume
+pub fn resume_live_capture(
+    live_capture_settings: &mut LiveCaptureOperationSettings,
+    save_settings: &SavePathList,
+    capture_manifest: &mut CaptureManifestRecord,
+    window_query: &mut Query<&mut Window, Without<PrimaryWindow>>,
+    model_animation: &mut ModelAnimation,
+) -> bool {
+    let dir_path = save_settings.save_path_list[save_settings.current_path_count as usize]
+        .current_dir_path
+        .clone();
+    let progress = match read_capture_progress(Path::new(&dir_path)) {
+        Some(progress) => progress,
+        None => {
+            println!("💾 No live capture to resume");
+            return false;
+        }
+    };
+    if progress.current_counter >= progress.coordinate_list.len() {
+        println!("✅ Live capture already finished");
+        return false;
+    }
+
+    live_capture_settings.live_capture_iteration = progress.coordinate_list.len();
+    live_capture_settings.live_capture_coordinate_list = progress.coordinate_list.clone();
+    live_capture_settings.live_capture_animation_times = progress.animation_times.clone();
+    if let Some(animation_clip) = progress.animation_clip {
+        model_animation.current_clip = animation_clip;
+    }
+    live_capture_settings.live_capture_iteration_current_counter = progress.current_counter;
+    // the saved seed only drive this run, the next run use the settings again
+    live_capture_settings.live_capture_seed = progress.seed;
+    live_capture_settings.skip_existing = true;
+
+    // keep the frames that were already recorded, the manifest is only missing if MVC didn't exit properly
+    capture_manifest.manifest =
+        read_capture_manifest(Path::new(&dir_path)).unwrap_or_else(|| CaptureManifest {
+            seed: progress.seed,
+            viewpoint_sampler: progress.viewpoint_sampler.clone(),
+            ..default()
+        });
+    capture_manifest.manifest.frames =
+        saved_frames(Path::new(&dir_path), &capture_manifest.manifest.frames);
+    capture_manifest.dir_path = dir_path;
+    capture_manifest.progress = Some(progress);
+
+    for mut window in window_query.iter_mut() {
+        window.title = format!(
+            "Live Capturing 🎥 [{}/{}]",
+            live_capture_settings.live_capture_iteration_current_counter,
+            live_capture_settings.live_capture_iteration
+        );
+    }
+    return true;
+}
+
+// the frames are recorded when their screenshot is requested, so the ones still in flight when MVC was closed have no
+// snapshot on disk and are captured again by the resumed run, only the last record of a snapshot is kept
+fn saved_frames(dir_path: &Path, frames: &[FrameRecord]) -> Vec<FrameRecord> {
+    let mut saved_frames: Vec<FrameRecord> = vec![];
+    for frame in frames {
+        if !dir_path.join(&frame.file_name).exists() {
+            continue;
+        }
+        saved_frames.retain(|saved_frame| saved_frame.file_name != frame.file_name);
+        saved_frames.push(frame.clone());
+    }
+
+    return saved_frames;
+}
+
+// the directory name and file prefix of the snapshots for a model are both taken from the model file name, the
+// directory is placed under sub_dir_path to mirror the folder the model was imported from
+pub fn build_save_path(base_dir_path: &str, sub_dir_path: &Path, model_path: &Path) -> SavePath {
+    let filename = model_path
+        .file_stem()
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    let current_dir_path = PathBuf::from(base_dir_path)
+        .join(sub_dir_path)
+        .join(&filename)
+        .to_str()
+        .unwrap()
+        .to_string();
+
+    return SavePath {
+        current_dir_path,
+        file_name_prefix: filename,
+        output_dir_path: base_dir_path.to_string(),
+    };
+}
+
+// the name of every scene of a glTF file, only the JSON chunk of a glb file is parsed
+fn gltf_scene_names(model_path: &Path) -> Result<Vec<String>, String> {
+    let bytes = std::fs::read(model_path)
+        .map_err(|err| format!("unable to read {:?}: {}", model_path, err))?;
+    let json_bytes = if bytes.starts_with(b"glTF") {
+        // the 12 bytes glb header is followed by the JSON chunk length and type, then the JSON itself
+        if bytes.len() < 20 {
+            return Err(format!(
+                "invalid glb file {:?}: truncated header",
+                model_path
+            ));
+        }
+        let chunk_length =
+            u32::from_le_bytes([bytes[12], bytes[13], bytes[14], bytes[15]]) as usize;
+        match bytes.get(20..20 + chunk_length) {
+            Some(json_bytes) => json_bytes,
+            None => {
+                return Err(format!(
+                    "invalid glb file {:?}: truncated JSON chunk",
+                    model_path
+                ))
+            }
+        }
+    } else {
+        &bytes[..]
+    };
+
+    let gltf: serde_json::Value = serde_json::from_slice(json_bytes)
+        .map_err(|err| format!("invalid glTF file {:?}: {}", model_path, err))?;
+    return Ok(match gltf["scenes"].as_array() {
+        Some(scenes) => scenes
+            .iter()
+            .map(|scene| scene["name"].as_str().unwrap_or("").to_string())
+            .collect(),
+        None => vec![],
+    });
+}
+
+// a model file with the same name as a model file of another folder would share its save directory, the directory name
+// and file prefix get a numeric suffix ( model_2, model_3, ... ) until they are free, the entries of the model file
+// itself don't count so that adding it again keep its directory
+fn unique_save_path(
+    save_path: SavePath,
+    model_path: &Path,
+    models_path: &[String],
+    save_path_list: &[SavePath],
+) -> SavePath {
+    let string_path = model_path.to_str().unwrap();
+    let taken_dir_paths: Vec<&Path> = models_path
+        .iter()
+        .zip(save_path_list.iter())
+        .filter(|(entry, _)| entry.split('#').next() != Some(string_path))
+        .map(|(_, taken_save_path)| Path::new(&taken_save_path.current_dir_path))
+        .collect();
+    let is_taken = |dir_path: &Path| {
+        return taken_dir_paths
+            .iter()
+            .any(|taken_dir_path| taken_dir_path.starts_with(dir_path));
+    };
+    if !is_taken(Path::new(&save_path.current_dir_path)) {
+        return save_path;
+    }
+
+    let parent_dir_path = PathBuf::from(&save_path.current_dir_path)
+        .parent()
+        .unwrap_or(Path::new(""))
+        .to_path_buf();
+    let mut suffix = 2;
+    loop {
+        let file_name_prefix = format!("{}_{}", save_path.file_name_prefix, suffix);
+        let current_dir_path = parent_dir_path.join(&file_name_prefix);
+        if !is_taken(&current_dir_path) {
+            return SavePath {
+                current_dir_path: current_dir_path.to_str().unwrap().to_string(),
+                file_name_prefix,
+                output_dir_path: save_path.output_dir_path,
+            };
+        }
+        suffix += 1;
+    }
+}
+
+// the entries to render for a model file with their save path, only a glTF file can give more than 1 entry ( one per
+// scene ), the entry of a scene other than the first one is the file path with the scene label ( model.glb#Scene2 )
+pub fn model_entries(
+    save_path: SavePath,
+    model_path: &Path,
+    gltf_scene: &GltfSceneSelection,
+) -> Result<Vec<(String, SavePath)>, String> {
+    let string_path = model_path.to_str().unwrap().to_string();
+    let is_gltf = match model_path.extension() {
+        Some(ext) => ext == "glb" || ext == "gltf",
+        None => false,
+    };
+    if !is_gltf || *gltf_scene == GltfSceneSelection::default() {
+        return Ok(vec![(string_path, save_path)]);
+    }
+
+    let scene_entry = |index: usize| {
+        if index == 0 {
+            return string_path.clone();
+        }
+        return format!("{}#Scene{}", string_path, index);
+    };
+    let scene_names = gltf_scene_names(model_path)?;
+    match gltf_scene {
+        GltfSceneSelection::Index { index } => {
+            if *index >= scene_names.len() {
+                return Err(format!(
+                    "{:?} has no scene {} ( {} scene(s) )",
+                    model_path,
+                    index,
+                    scene_names.len()
+                ));
+            }
+            return Ok(vec![(scene_entry(*index), save_path)]);
+        }
+        GltfSceneSelection::Name { name } => {
+            match scene_names.iter().position(|scene_name| scene_name == name) {
+                Some(index) => return Ok(vec![(scene_entry(index), save_path)]),
+                None => return Err(format!("{:?} has no scene named {}", model_path, name)),
+            }
+        }
+        GltfSceneSelection::All => {
+            if scene_names.len() <= 1 {
+                return Ok(vec![(string_path, save_path)]);
+            }
+            // every scene is saved in its own sub directory of the model's directory
+            return Ok(scene_names
+                .iter()
+                .enumerate()
+                .map(|(index, scene_name)| {
+                    let mut scene_dir_name = format!("scene_{}", index);
+                    if !scene_name.is_empty() {
+                        let scene_name: String = scene_name
+                            .chars()
+                            .map(|c| {
+                                if c.is_alphanumeric() || c == '-' {
+                                    c
+                                } else {
+                                    '_'
+                                }
+                            })
+                            .collect();
+                        scene_dir_name = format!("{}_{}", scene_dir_name, scene_name);
+                    }
+                    let scene_save_path = SavePath {
+                        current_dir_path: PathBuf::from(&save_path.current_dir_path)
+                            .join(&scene_dir_name)
+                            .to_str()
+                            .unwrap()
+                            .to_string(),
+                        file_name_prefix: format!(
+                            "{}_{}",
+                            save_path.file_name_prefix, scene_dir_name
+                        ),
+                        output_dir_path: save_path.output_dir_path.clone(),
+                    };
+                    (scene_entry(index), scene_save_path)
+                })
+                .collect());
+        }
+    }
+}
+
+// add the entries of a model file that aren't there yet, with their save path and the model's overrides, return the
+// number of new entries
+pub fn add_model_entries(
+    asset_path: &mut AssetPath,
+    save_settings: &mut SavePathList,
+    base_dir_path: &str,
+    sub_dir_path: &Path,
+    model_path: &Path,
+    model_overrides: Option<ModelOverrides>,
+) -> Result<usize, String> {
+    let gltf_scene = model_overrides
+        .as_ref()
+        .and_then(|overrides| overrides.gltf_scene.clone())
+        .unwrap_or(asset_path.gltf_scene.clone());
+    let save_path = unique_save_path(
+        build_save_path(base_dir_path, sub_dir_path, model_path),
+        model_path,
+        &asset_path.models_path,
+        &save_settings.save_path_list,
+    );
+    let mut new_entry_count = 0;
+    for (entry, save_path) in model_entries(save_path, model_path, &gltf_scene)? {
+        if asset_path.models_path.contains(&entry) {
+            continue;
+        }
+        if let Some(overrides) = &model_overrides {
+            asset_path
+                .model_overrides
+                .insert(entry.clone(), overrides.clone());
+        }
+        asset_path.models_path.push(entry);
+        save_settings.save_path_list.push(save_path);
+        new_entry_count += 1;
+    }
+
+    return Ok(new_entry_count);
+}
+
+// parse the command line arguments for the headless batch capture mode, no argument means MVC start with the main menu as usual
+//
+// usage: mvc --skybox <skybox file> --output <output directory> <model file> [<model file> ...]
+pub fn parse_batch_args(args: Vec<String>) -> Result<Option<BatchCaptureArgs>, String> {
+    if args.is_empty() {
+        return Ok(None);
+    }
+
+    let mut models_path: Vec<String> = vec![];
+    let mut skybox_path: Option<String> = None;
+    let mut output_dir: Option<String> = None;
+    let mut job_path: Option<String> = None;
+
+    let mut args_iter = args.into_iter();
+    while let Some(arg) = args_iter.next() {
+        match arg.as_str() {
+            "--skybox" => skybox_path = args_iter.next(),
+            "--output" => output_dir = args_iter.next(),
+            "--job" => job_path = args_iter.next(),
+            "-h" | "--help" => return Err(BATCH_USAGE.to_string()),
+            _ => {
+                if arg.starts_with("--") {
+                    return Err(format!("unknown option {}\n{}", arg, BATCH_USAGE));
+                }
+                models_path.push(arg);
+            }
+        }
+    }
+
+    // the command line arguments take priority over the capture job
+    let mut model_overrides = HashMap::new();
+    if let Some(job_path) = job_path {
+        let job = load_capture_job(Path::new(&job_path))?;
+        skybox_path = skybox_path.or(job.skybox_path);
+        output_dir = output_dir.or(job.output_dir);
+        for job_model in job.models {
+            if !models_path.contains(&job_model.path) {
+                models_path.push(job_model.path.clone());
+            }
+            model_overrides.insert(job_model.path, job_model.overrides);
+        }
+    }
+
+    if models_path.is_empty() {
+        return Err(format!(
+            "at least 1 model file is required\n{}",
+            BATCH_USAGE
+        ));
+    }
+    for model_path in models_path.iter() {
+        if !check_model_file(model_path) || !Path::new(model_path).is_file() {
+            return Err(format!("invalid model file {}", model_path));
+        }
+    }
+
+    let skybox_path = match skybox_path {
+        Some(skybox_path) => skybox_path,
+        None => return Err(format!("--skybox is required\n{}", BATCH_USAGE)),
+    };
+    if !check_skybox_file(&skybox_path) || !Path::new(&skybox_path).is_file() {
+        return Err(format!("invalid skybox file {}", skybox_path));
+    }
+
+    let output_dir = match output_dir {
+        Some(output_dir) => output_dir,
+        None => return Err(format!("--output is required\n{}", BATCH_USAGE)),
+    };
+    if let Err(err) = create_dir_all(&output_dir) {
+        return Err(format!(
+            "unable to create output directory {}: {}",
+            output_dir, err
+        ));
+    }
+
+    return Ok(Some(BatchCaptureArgs {
+        models_path,
+        skybox_path,
+        output_dir,
+        model_overrides,
+    }));
+}
+
+// a capture job is told apart from a settings file by its list of models
+pub fn is_capture_job_file(file_path: &Path) -> bool {
+    return match File::open(file_path) {
+        Ok(file) => match serde_json::from_reader::<_, serde_json::Value>(file) {
+            Ok(value) => value.get("models").is_some(),
+            Err(_) => false,
+        },
+        Err(_) => false,
+    };
+}
+
+// read a capture job file, the relative paths inside it are resolved from the job file's directory
+pub fn load_capture_job(job_path: &Path) -> Result<CaptureJob, String> {
+    let file = match File::open(job_path) {
+        Ok(file) => file,
+        Err(err) => {
+            return Err(format!(
+                "unable to open capture job {:?}: {}",
+                job_path, err
+            ))
+        }
+    };
+    let mut job: CaptureJob = match serde_json::from_reader(file) {
+        Ok(job) => job,
+        Err(err) => return Err(format!("invalid capture job {:?}: {}", job_path, err)),
+    };
+
+    let job_dir_path = job_path.parent().unwrap_or(Path::new(""));
+    let resolve = |path: &str| job_dir_path.join(path).to_string_lossy().to_string();
+    job.output_dir = job.output_dir.as_deref().map(resolve);
+    job.skybox_path = job.skybox_path.as_deref().map(resolve);
+    for job_model in job.models.iter_mut() {
+        job_model.path = resolve(&job_model.path);
+        job_model.overrides.skybox_path = job_model.overrides.skybox_path.as_deref().map(resolve);
+        if !check_model_file(&job_model.path) || !Path::new(&job_model.path).is_file() {
+            return Err(format!("invalid model file {}", job_model.path));
+        }
+        if let Some(skybox_path) = job_model.overrides.skybox_path.as_ref() {
+            if !check_skybox_file(skybox_path) || !Path::new(skybox_path).is_file() {
+                return Err(format!("invalid skybox file {}", skybox_path));
+            }
+        }
+    }
+
+    return Ok(job);
+}
+
+// a lighting rig file is told apart from a settings file by its list of lights
+pub fn is_lighting_rig_file(file_path: &Path) -> bool {
+    return match File::open(file_path) {
+        Ok(file) => match serde_json::from_reader::<_, serde_json::Value>(file) {
+            Ok(value) => value.get("lights").is_some(),
+            Err(_) => false,
+        },
+        Err(_) => false,
+    };
+}
+
+pub fn load_lighting_rig(rig_path: &Path) -> Result<LightingRig, String> {
+    let file = match File::open(rig_path) {
+        Ok(file) => file,
+        Err(err) => {
+            return Err(format!(
+                "unable to open lighting rig {:?}: {}",
+                rig_path, err
+            ))
+        }
+    };
+    return match serde_json::from_reader(file) {
+        Ok(rig) => Ok(rig),
+        Err(err) => Err(format!("invalid lighting rig {:?}: {}", rig_path, err)),
+    };
+}
+
+// the rig of the settings, the rig file take the place of the rig written in the settings when it can be read
+pub fn settings_lighting_rig(app_settings: &AppSettings) -> LightingRig {
+    if let Some(rig_path) = app_settings.lighting_rig_path.as_ref() {
+        match load_lighting_rig(Path::new(rig_path)) {
+            Ok(rig) => return rig,
+            Err(err) => eprintln!(
+                "❌ {}, the lighting rig of the settings is used instead",
+                err
+            ),
+        }
+    }
+
+    return app_settings.lighting_rig.clone();
+}
+
 pub fn init_app() -> AppSettings {
     let image_save_dir = get_user_directory()
         .join("Downloads")
@@ -233,6 +949,21 @@ pub fn init_app() -> AppSettings {
             mouse_sensitivity: MOUSE_SENSITIVITY,
             zoom_sensitivity: ZOOM_SENSITIVITY,
             live_capture_iteration: LIVE_CAPTURE_ITERATION,
+            depth_output: DepthOutputFormat::None,
+            normal_output: NormalOutputSpace::None,
+            mask_output: false,
+            rgba_output: false,
+            instance_output: false,
+            viewpoint_sampler: ViewpointSampler::Halton,
+            seed: 0,
+            max_snapshots_in_flight: 1,
+            gltf_scene: GltfSceneSelection::default(),
+            animation_capture: None,
+            lighting_randomization: None,
+            lighting_rig: LightingRig::default(),
+            lighting_rig_path: None,
+            ground_plane: None,
+            skybox_pool: None,
         };
 
         create_file_with_dirs(settings_file_path.to_str().unwrap());
@@ -273,38 +1004,7 @@ fn create_file_with_dirs(path: &str) {
     File::create(path).unwrap();
 }
 
-// Generate the n-th term of a Halton sequence for a given base
-fn halton(index: u32, base: u32) -> f32 {
-    let mut result = 0.0;
-    let mut f = 1.0;
-    let mut i = index;
-
-    while i > 0 {
-        f /= base as f32;
-        result += f * (i % base) as f32;
-        i /= base;
-    }
-
-    return result;
-}
-
-// Generate 3D points within specified ranges using the Halton sequence
-fn generate_points(
-    count: usize,
-    x_range: (f32, f32),
-    y_range: (f32, f32),
-    z_range: (f32, f32),
-) -> Vec<(f32, f32, f32)> {
-    return (0..count)
-        .map(|i| {
-            let x = x_range.0 + (x_range.1 - x_range.0) * halton(i as u32 + 1, 2);
-            let y = y_range.0 + (y_range.1 - y_range.0) * halton(i as u32 + 1, 3);
-            let z = z_range.0 + (z_range.1 - z_range.0) * halton(i as u32 + 1, 5);
-            return (x, y, z);
-        })
-        .collect();
-}
-
+// create the save directory of the current model if it doesn't exist yet
 fn snapshot_directory_init(save_settings: SavePathList) {
     let snapshot_path = Path::new(
         &save_settings.save_path_list[save_settings.current_path_count as usize].current_dir_path,
@@ -313,3 +1013,289 @@ fn snapshot_directory_init(save_settings: SavePathList) {
         create_dir_all(&snapshot_path).unwrap();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TWO_SCENES: &str =
+        r#"{"asset":{"version":"2.0"},"scenes":[{"name":"Base"},{"name":"Low Poly"}]}"#;
+
+    // write the file in its own temporary directory, so that the tests running at the same time don't share files
+    fn write_model(test_name: &str, file_name: &str, bytes: &[u8]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mvc_{}_{}", test_name, std::process::id()));
+        create_dir_all(&dir).unwrap();
+        let path = dir.join(file_name);
+        std::fs::write(&path, bytes).unwrap();
+        return path;
+    }
+
+    // a glb with its JSON chunk padded to 4 bytes and a binary chunk after it
+    fn glb(json: &str) -> Vec<u8> {
+        let mut json_bytes = json.as_bytes().to_vec();
+        while json_bytes.len() % 4 != 0 {
+            json_bytes.push(b' ');
+        }
+        let binary = [0u8; 8];
+        let total_length = 12 + 8 + json_bytes.len() + 8 + binary.len();
+
+        let mut bytes = b"glTF".to_vec();
+        bytes.extend(2u32.to_le_bytes());
+        bytes.extend((total_length as u32).to_le_bytes());
+        bytes.extend((json_bytes.len() as u32).to_le_bytes());
+        bytes.extend(b"JSON");
+        bytes.extend(&json_bytes);
+        bytes.extend((binary.len() as u32).to_le_bytes());
+        bytes.extend(b"BIN\0");
+        bytes.extend(binary);
+        return bytes;
+    }
+
+    fn entries(
+        model_path: &Path,
+        gltf_scene: GltfSceneSelection,
+    ) -> Result<Vec<(String, SavePath)>, String> {
+        return model_entries(
+            build_save_path("out", Path::new("sub"), model_path),
+            model_path,
+            &gltf_scene,
+        );
+    }
+
+    #[test]
+    fn glb_scene_names_come_from_the_json_chunk() {
+        let path = write_model("glb_scene_names", "model.glb", &glb(TWO_SCENES));
+
+        assert_eq!(
+            gltf_scene_names(&path),
+            Ok(vec!["Base".to_string(), "Low Poly".to_string()])
+        );
+    }
+
+    #[test]
+    fn gltf_scene_names_come_from_the_json() {
+        let path = write_model(
+            "gltf_scene_names",
+            "model.gltf",
+            br#"{"scenes":[{"nodes":[0]},{"name":"Second"}]}"#,
+        );
+
+        assert_eq!(
+            gltf_scene_names(&path),
+            Ok(vec!["".to_string(), "Second".to_string()])
+        );
+    }
+
+    #[test]
+    fn short_gltf_files_are_read_whole() {
+        let short = write_model("short_gltf", "short.gltf", br#"{"scenes":[{}]}"#);
+        assert_eq!(gltf_scene_names(&short), Ok(vec!["".to_string()]));
+
+        let empty = write_model("short_gltf", "empty.gltf", b"");
+        assert!(gltf_scene_names(&empty)
+            .unwrap_err()
+            .starts_with("invalid glTF file"));
+    }
+
+    #[test]
+    fn truncated_glb_files_are_rejected() {
+        let header = write_model("truncated_glb", "header.glb", b"glTF\x02\0\0\0");
+        assert!(gltf_scene_names(&header)
+            .unwrap_err()
+            .ends_with("truncated header"));
+
+        let mut bytes = glb(TWO_SCENES);
+        bytes.truncate(40);
+        let chunk = write_model("truncated_glb", "chunk.glb", &bytes);
+        assert!(gltf_scene_names(&chunk)
+            .unwrap_err()
+            .ends_with("truncated JSON chunk"));
+    }
+
+    #[test]
+    fn scene_index_selects_the_scene_label() {
+        let path = write_model("scene_index", "model.glb", &glb(TWO_SCENES));
+        let string_path = path.to_str().unwrap().to_string();
+
+        let first = entries(&path, GltfSceneSelection::Index { index: 0 }).unwrap();
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].0, string_path);
+
+        let second = entries(&path, GltfSceneSelection::Index { index: 1 }).unwrap();
+        assert_eq!(second[0].0, format!("{}#Scene1", string_path));
+        assert_eq!(second[0].1.file_name_prefix, "model");
+    }
+
+    #[test]
+    fn scene_index_out_of_range_is_an_error() {
+        let path = write_model("scene_index_out_of_range", "model.glb", &glb(TWO_SCENES));
+
+        assert_eq!(
+            entries(&path, GltfSceneSelection::Index { index: 2 }).err(),
+            Some(format!("{:?} has no scene 2 ( 2 scene(s) )", path))
+        );
+    }
+
+    #[test]
+    fn scene_name_selects_the_matching_scene() {
+        let path = write_model("scene_name", "model.gltf", TWO_SCENES.as_bytes());
+
+        let low_poly = entries(
+            &path,
+            GltfSceneSelection::Name {
+                name: "Low Poly".to_string(),
+            },
+        )
+        .unwrap();
+        assert_eq!(low_poly[0].0, format!("{}#Scene1", path.to_str().unwrap()));
+
+        assert_eq!(
+            entries(
+                &path,
+                GltfSceneSelection::Name {
+                    name: "High Poly".to_string(),
+                },
+            )
+            .err(),
+            Some(format!("{:?} has no scene named High Poly", path))
+        );
+    }
+
+    #[test]
+    fn all_scenes_of_a_single_scene_file_is_the_file_itself() {
+        let path = write_model(
+            "all_single_scene",
+            "model.glb",
+            &glb(r#"{"scenes":[{"name":"Only"}]}"#),
+        );
+
+        let all = entries(&path, GltfSceneSelection::All).unwrap();
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].0, path.to_str().unwrap());
+        assert_eq!(
+            all[0].1.current_dir_path,
+            build_save_path("out", Path::new("sub"), &path).current_dir_path
+        );
+    }
+
+    #[test]
+    fn all_scenes_get_their_own_directory() {
+        let path = write_model("all_scenes", "model.glb", &glb(TWO_SCENES));
+
+        let all = entries(&path, GltfSceneSelection::All).unwrap();
+        let string_path = path.to_str().unwrap().to_string();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].0, string_path);
+        assert_eq!(all[1].0, format!("{}#Scene1", string_path));
+        assert_eq!(all[1].1.file_name_prefix, "model_scene_1_Low_Poly");
+        assert_eq!(
+            PathBuf::from(&all[1].1.current_dir_path),
+            PathBuf::from("out")
+                .join("sub")
+                .join("model")
+                .join("scene_1_Low_Poly")
+        );
+        assert_eq!(all[1].1.output_dir_path, "out");
+    }
+
+    #[test]
+    fn other_files_are_never_read() {
+        // neither file exist, the default selection and a non glTF model don't need the scenes
+        for (model_path, gltf_scene) in [
+            ("missing.glb", GltfSceneSelection::default()),
+            ("missing.obj", GltfSceneSelection::All),
+        ] {
+            let all = entries(Path::new(model_path), gltf_scene).unwrap();
+            assert_eq!(all.len(), 1);
+            assert_eq!(all[0].0, model_path);
+        }
+    }
+
+    fn frame(file_name: &str, yaw: f32) -> FrameRecord {
+        return serde_json::from_value(serde_json::json!({
+            "file_name": file_name,
+            "yaw": yaw,
+            "pitch": 0.0,
+            "radius": 1.0,
+            "camera_to_world": [[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]],
+            "intrinsics": {
+                "width": 4, "height": 4, "fov_y": 1.0, "aspect_ratio": 1.0, "near": 0.1, "far": 1000.0,
+                "fx": 2.0, "fy": 2.0, "cx": 2.0, "cy": 2.0
+            }
+        }))
+        .unwrap();
+    }
+
+    #[test]
+    fn resumed_frames_only_keep_the_saved_snapshots_once() {
+        let dir = write_model("saved_frames", "a.jpg", b"")
+            .parent()
+            .unwrap()
+            .to_path_buf();
+        write_model("saved_frames", "b.jpg", b"");
+
+        let frames = saved_frames(
+            &dir,
+            &[
+                frame("a.jpg", 0.0),
+                frame("b.jpg", 1.0),
+                // the screenshot was still in flight
+                frame("c.jpg", 2.0),
+                // recorded again by an earlier resume
+                frame("a.jpg", 3.0),
+            ],
+        );
+
+        let frames: Vec<(&str, f32)> = frames
+            .iter()
+            .map(|frame| (frame.file_name.as_str(), frame.yaw))
+            .collect();
+        assert_eq!(frames, vec![("b.jpg", 1.0), ("a.jpg", 3.0)]);
+    }
+
+    #[test]
+    fn same_model_names_from_different_folders_get_their_own_directory() {
+        let mut asset_path = AssetPath {
+            models_path: vec![],
+            skybox_path: String::new(),
+            current_model_path_count: 0,
+            model_overrides: HashMap::new(),
+            gltf_scene: GltfSceneSelection::default(),
+        };
+        let mut save_settings = SavePathList {
+            base_dir_path: "out".to_string(),
+            save_path_list: vec![],
+            current_path_count: 0,
+        };
+        for model_path in ["a/model.obj", "b/model.obj", "c/model.obj", "a/model.obj"] {
+            add_model_entries(
+                &mut asset_path,
+                &mut save_settings,
+                "out",
+                Path::new(""),
+                Path::new(model_path),
+                None,
+            )
+            .unwrap();
+        }
+
+        let save_paths: Vec<(&str, &str)> = save_settings
+            .save_path_list
+            .iter()
+            .map(|save_path| {
+                (
+                    save_path.current_dir_path.as_str(),
+                    save_path.file_name_prefix.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            save_paths,
+            vec![
+                ("out/model", "model"),
+                ("out/model_2", "model_2"),
+                ("out/model_3", "model_3")
+            ]
+        );
+    }
+}